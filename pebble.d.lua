@@ -0,0 +1,143 @@
+---@meta
+-- Generated by pebble from the rust types, do not edit.
+-- Run `cargo run -- types` to regenerate it.
+
+---@class Vector
+---@field x number
+---@field y number
+
+---@class Transform
+---@field position Vector
+---@field rotation number
+---@field scale Vector
+
+---@class Color
+---@field r number
+---@field g number
+---@field b number
+---@field a number
+
+---@class Material
+---@field albedo Color
+---@field texture string|nil
+---@field shader string|nil
+---@field uniforms table<string, number|number[]>|nil
+---@field blend string|nil
+---@field normal_map string|nil
+
+---@class Component
+---@field c_type string
+---@field data table
+
+---@class Region
+---@field x number
+---@field y number
+---@field w number
+---@field h number
+
+---@class Borders
+---@field left number
+---@field top number
+---@field right number
+---@field bottom number
+
+---@class Sprite
+---@field sheet string|nil
+---@field frame string|nil
+---@field region Region|nil
+---@field size Vector|nil
+---@field pivot Vector|nil
+---@field flip_x boolean|nil
+---@field flip_y boolean|nil
+---@field slice Borders|nil
+
+---@class Animator
+---@field animation string|nil
+---@field clip string|nil
+---@field speed number|nil
+---@field playing boolean|nil
+---@field frame number|nil
+---@field time number|nil
+---@field direction number|nil
+---@field started boolean|nil
+---@field cycle number|nil
+
+---@class Tilemap
+---@field map string|nil
+---@field loaded boolean|nil
+
+---@class Layer
+---@field name string|nil
+---@field z_index number|nil
+
+---@class Camera
+---@field active boolean|nil
+---@field order number|nil
+---@field layers string[]|nil
+---@field position Vector|nil
+---@field zoom number|nil
+---@field rotation number|nil
+---@field viewport Region|nil
+---@field follow string|nil
+---@field smoothing number|nil
+---@field bounds Region|nil
+---@field shake_intensity number|nil
+---@field shake_duration number|nil
+---@field shake_time number|nil
+---@field offset Vector|nil
+---@field target string|nil
+---@field screen Region|nil
+
+---@class Text
+---@field text string|nil
+---@field font string|nil
+---@field size number|nil
+---@field color Color|nil
+---@field align string|nil
+---@field wrap_width number|nil
+
+---@class RenderTarget
+---@field active boolean|nil
+---@field name string|nil
+---@field width number
+---@field height number
+---@field order number|nil
+---@field layers string[]|nil
+---@field clear_color Color|nil
+
+---@class ParticleEmitter
+---@field emitting boolean|nil
+---@field file string|nil
+---@field rate number|nil
+---@field burst number|nil
+---@field lifetime Vector|nil
+---@field speed Vector|nil
+---@field direction number|nil
+---@field spread number|nil
+---@field gravity Vector|nil
+---@field colors Color[]|nil
+---@field sizes number[]|nil
+---@field texture string|nil
+---@field local_space boolean|nil
+---@field max_particles number|nil
+
+---@class AmbientLight
+---@field color Color|nil
+---@field intensity number|nil
+
+---@class PointLight
+---@field color Color|nil
+---@field intensity number|nil
+---@field radius number|nil
+---@field falloff number|nil
+---@field height number|nil
+
+---@class Mouse
+---@field position Vector
+---@field left boolean
+---@field right boolean
+---@field middle boolean
+
+---@class LuaAsset
+---@field filename string
+---@field file_type string
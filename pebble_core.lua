@@ -1,6 +1,10 @@
 require("pebble_log")
+require("pebble_events")
+require("pebble_graphics")
+require("pebble_debug")
+require("pebble_screen")
 
-Assets = { textures = {} }
+Assets = { textures = {}, sprite_sheets = {}, animations = {}, aseprite = {}, tilemaps = {}, fonts = {}, shaders = {}, particles = {} }
 
 ---@class Array
 ---@field count number The number of element in the array 
@@ -39,8 +43,6 @@ function Array:filter(callback)
 end
 
 ---@class Component
----@field c_type string
----@field data any
 Component = {}
 
 ---@param type string
@@ -83,9 +85,16 @@ function Assets:add_image(filename)
     }
 end
 
+---Load a sprite sheet json file and its image
+---@param filename string
+function Assets:add_sprite_sheet(filename)
+    self["sprite_sheets"][filename] = {
+        file_type = "sprite_sheet",
+        filename = filename
+    }
+end
+
 ---@class Vector
----@field x number
----@field y number
 Vector = {}
 
 ---@param x number
@@ -99,9 +108,6 @@ function Vector:new(x, y)
 end
 
 ---@class Transform : Component
----@field position Vector
----@field scale Vector
----@field rotation number
 Transform = {}
 
 ---@return Transform
@@ -125,10 +131,6 @@ function Transform:type()
 end
 
 ---@class Color
----@field r number
----@field g number
----@field b number
----@field a number
 Color = {r = 0.0, g = 0.0, b = 0.0, a = 0.0}
 
 ---@param r number
@@ -144,12 +146,10 @@ function Color:new(r, g, b, a)
 end
 
 ---@class Material : Component
----@field albedo Color
----@field texture string|nil
 Material = {}
 
 function Material:new()
-    local o = { albedo = Color:new(255, 255, 255, 255), texture = nil }
+    local o = { albedo = Color:new(255, 255, 255, 255), texture = nil, shader = nil, uniforms = {}, blend = "alpha", normal_map = nil }
     setmetatable(o, self)
     self.__index = self
     return o
@@ -163,4 +163,448 @@ end
 ---@return string
 function Material:type()
     return "Material"
-end
\ No newline at end of file
+end
+
+---Load an animation json file and its sprite sheet
+---@param filename string
+function Assets:add_animation(filename)
+    self["animations"][filename] = {
+        file_type = "animation",
+        filename = filename
+    }
+end
+
+---Load a json file exported by aseprite and its image, usable as a sprite sheet and as an animation
+---@param filename string
+function Assets:add_aseprite(filename)
+    self["aseprite"][filename] = {
+        file_type = "aseprite",
+        filename = filename
+    }
+end
+
+---Load a map made with tiled (.tmx, .tmj or .json), its tilesets and their images
+---@param filename string
+function Assets:add_tilemap(filename)
+    self["tilemaps"][filename] = {
+        file_type = "tilemap",
+        filename = filename
+    }
+end
+
+---Load a ttf or otf font, used by `Text` components with its filename
+---@param filename string
+function Assets:add_font(filename)
+    self["fonts"][filename] = {
+        file_type = "font",
+        filename = filename
+    }
+end
+
+---Load the settings of a particle emitter from a json file
+---@param filename string
+function Assets:add_particles(filename)
+    self["particles"][filename] = {
+        file_type = "particles",
+        filename = filename
+    }
+end
+
+---Load a wgsl fragment shader, used by `Material` components with its filename
+---@param filename string
+function Assets:add_shader(filename)
+    self["shaders"][filename] = {
+        file_type = "shader",
+        filename = filename
+    }
+end
+
+---@class Region
+Region = {}
+
+---@param x number
+---@param y number
+---@param w number
+---@param h number
+---@return Region
+function Region:new(x, y, w, h)
+    local o = {x = x, y = y, w = w, h = h}
+    setmetatable(o, self)
+    self.__index = self
+    return o
+end
+
+---@class Borders
+Borders = {}
+
+---@param left number
+---@param top number|nil default to left
+---@param right number|nil default to left
+---@param bottom number|nil default to top
+---@return Borders
+function Borders:new(left, top, right, bottom)
+    top = top or left
+    local o = { left = left, top = top, right = right or left, bottom = bottom or top }
+    setmetatable(o, self)
+    self.__index = self
+    return o
+end
+
+---@class Sprite : Component
+Sprite = {}
+
+---@return Sprite
+function Sprite:new()
+    local o = { pivot = Vector:new(0.5, 0.5), flip_x = false, flip_y = false }
+    setmetatable(o, self)
+    self.__index = self
+    return o
+end
+
+---@return Sprite
+function Sprite:default()
+    return Sprite:new()
+end
+
+---@return string
+function Sprite:type()
+    return "Sprite"
+end
+
+---@class Animator : Component
+Animator = {}
+
+---@return Animator
+function Animator:new()
+    local o = { speed = 1.0, playing = true, frame = 0, time = 0.0, direction = 1, started = false, cycle = 0, listeners = { ["end"] = {}, tag = {} } }
+    setmetatable(o, self)
+    self.__index = self
+    return o
+end
+
+---@return Animator
+function Animator:default()
+    return Animator:new()
+end
+
+---@return string
+function Animator:type()
+    return "Animator"
+end
+
+---Start a clip from its first frame
+---@param clip string
+function Animator:play(clip)
+    self.clip = clip
+    self.frame = 0
+    self.time = 0.0
+    self.direction = 1
+    self.playing = true
+    self.started = false
+    self.cycle = 0
+end
+
+---@param callback fun(clip: string)
+function Animator:on_end(callback)
+    table.insert(self.listeners["end"], callback)
+end
+
+---@param tag string
+---@param callback fun(clip: string, tag: string)
+function Animator:on_tag(tag, callback)
+    if self.listeners.tag[tag] == nil then
+        self.listeners.tag[tag] = {}
+    end
+
+    table.insert(self.listeners.tag[tag], callback)
+end
+
+---Called by pebble when the clip end or when a tagged frame start
+---@param event string
+---@param clip string
+---@param tag string|nil
+function Animator:dispatch(event, clip, tag)
+    local listeners = self.listeners[event]
+
+    if event == "tag" then
+        listeners = self.listeners.tag[tag]
+    end
+
+    if listeners == nil then
+        return
+    end
+
+    for _, callback in ipairs(listeners) do
+        callback(clip, tag)
+    end
+end
+
+---@class MapObject
+---@field id number
+---@field name string
+---@field class string
+---@field layer string
+---@field x number
+---@field y number
+---@field width number
+---@field height number
+---@field rotation number
+---@field properties table<string, any>
+
+---@class Tilemap : Component
+---@field objects MapObject[] Objects of every object layer, empty until the map is loaded
+---@field info table Size and custom properties of the map
+Tilemap = {}
+
+---@return Tilemap
+function Tilemap:new()
+    local o = { loaded = false, objects = {}, info = {}, listeners = {} }
+    setmetatable(o, self)
+    self.__index = self
+    return o
+end
+
+---@return Tilemap
+function Tilemap:default()
+    return Tilemap:new()
+end
+
+---@return string
+function Tilemap:type()
+    return "Tilemap"
+end
+
+---Called once the map is loaded, with the objects placed in tiled
+---@param callback fun(objects: MapObject[], info: table)
+function Tilemap:on_loaded(callback)
+    table.insert(self.listeners, callback)
+end
+
+---Called by pebble when the map is loaded
+---@param objects MapObject[]
+---@param info table
+function Tilemap:load(objects, info)
+    self.objects = objects
+    self.info = info
+    self.loaded = true
+
+    for _, callback in ipairs(self.listeners) do
+        callback(objects, info)
+    end
+end
+
+---@class Layer : Component
+Layer = {}
+
+---@param name string|nil Layer declared in pebble.json, "default" when nil
+---@param z_index number|nil
+---@return Layer
+function Layer:new(name, z_index)
+    local o = { name = name or "default", z_index = z_index or 0 }
+    setmetatable(o, self)
+    self.__index = self
+    return o
+end
+
+---@return Layer
+function Layer:default()
+    return Layer:new()
+end
+
+---@return string
+function Layer:type()
+    return "Layer"
+end
+
+---@class Camera : Component
+Camera = {}
+
+---@return Camera
+function Camera:new()
+    local o = {
+        active = true,
+        order = 0,
+        position = Vector:new(0.0, 0.0),
+        zoom = 1.0,
+        rotation = 0.0,
+        smoothing = 0.0,
+        shake_intensity = 0.0,
+        shake_duration = 0.0,
+        shake_time = 0.0,
+        offset = Vector:new(0.0, 0.0),
+        screen = Region:new(0.0, 0.0, 0.0, 0.0)
+    }
+    setmetatable(o, self)
+    self.__index = self
+    return o
+end
+
+---@return Camera
+function Camera:default()
+    return Camera:new()
+end
+
+---@return string
+function Camera:type()
+    return "Camera"
+end
+
+---Shake the view, the shake fade out during its duration
+---@param intensity number Offset in pixels
+---@param duration number Duration in seconds
+function Camera:shake(intensity, duration)
+    self.shake_intensity = intensity
+    self.shake_duration = duration
+    self.shake_time = duration
+end
+
+---Convert a position on the window (like the mouse position) to the world
+---@param x number
+---@param y number
+---@return Vector
+function Camera:screen_to_world(x, y)
+    return rust_camera:screen_to_world(self, Vector:new(x, y))
+end
+
+---Convert a position in the world to the window
+---@param x number
+---@param y number
+---@return Vector
+function Camera:world_to_screen(x, y)
+    return rust_camera:world_to_screen(self, Vector:new(x, y))
+end
+
+---@class Text : Component
+Text = {}
+
+---@param text string|nil
+---@return Text
+function Text:new(text)
+    local o = { text = text or "", size = 16.0, color = Color:new(255, 255, 255, 255), align = "left" }
+    setmetatable(o, self)
+    self.__index = self
+    return o
+end
+
+---@return Text
+function Text:default()
+    return Text:new()
+end
+
+---@return string
+function Text:type()
+    return "Text"
+end
+
+---@class RenderTarget : Component
+RenderTarget = {}
+
+---@param width number|nil
+---@param height number|nil
+---@return RenderTarget
+function RenderTarget:new(width, height)
+    local o = {
+        active = true,
+        width = width or 320,
+        height = height or 180,
+        order = 0,
+        clear_color = Color:new(0, 0, 0, 0)
+    }
+    setmetatable(o, self)
+    self.__index = self
+    return o
+end
+
+---@return RenderTarget
+function RenderTarget:default()
+    return RenderTarget:new()
+end
+
+---@return string
+function RenderTarget:type()
+    return "RenderTarget"
+end
+
+---@class ParticleEmitter : Component
+ParticleEmitter = {}
+
+---@return ParticleEmitter
+function ParticleEmitter:new()
+    -- The default settings are the ones of pebble
+    local o = rust_particles:default()
+    setmetatable(o, self)
+    self.__index = self
+    return o
+end
+
+---@return ParticleEmitter
+function ParticleEmitter:default()
+    return ParticleEmitter:new()
+end
+
+---@return string
+function ParticleEmitter:type()
+    return "ParticleEmitter"
+end
+
+---Emit particles at once on the next update
+---@param count number
+function ParticleEmitter:emit(count)
+    self.burst = self.burst + count
+end
+
+---@class AmbientLight : Component
+AmbientLight = {}
+
+---@param color Color|nil
+---@param intensity number|nil
+---@return AmbientLight
+function AmbientLight:new(color, intensity)
+    local o = { color = color or Color:new(255, 255, 255, 255), intensity = intensity or 1.0 }
+    setmetatable(o, self)
+    self.__index = self
+    return o
+end
+
+---@return AmbientLight
+function AmbientLight:default()
+    return AmbientLight:new()
+end
+
+---@return string
+function AmbientLight:type()
+    return "AmbientLight"
+end
+
+---@class PointLight : Component
+PointLight = {}
+
+---@param color Color|nil
+---@param radius number|nil
+---@return PointLight
+function PointLight:new(color, radius)
+    local o = {
+        color = color or Color:new(255, 255, 255, 255),
+        intensity = 1.0,
+        radius = radius or 128.0,
+        falloff = 1.0,
+        height = 32.0
+    }
+    setmetatable(o, self)
+    self.__index = self
+    return o
+end
+
+---@return PointLight
+function PointLight:default()
+    return PointLight:new()
+end
+
+---@return string
+function PointLight:type()
+    return "PointLight"
+end
+
+---State of the mouse, updated by pebble before `Update`
+---@type Mouse
+Mouse = { position = Vector:new(0.0, 0.0), left = false, right = false, middle = false }
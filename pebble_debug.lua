@@ -0,0 +1,38 @@
+---@meta
+
+---Debug shapes drawn on top of the scene for a single frame, in world coordinates.
+---Nothing is drawn in release mode, press F3 to show the gameobjects on the overlay.
+Debug = {}
+
+---@param x1 number
+---@param y1 number
+---@param x2 number
+---@param y2 number
+---@param color Color|nil White when nil
+function Debug:draw_line(x1, y1, x2, y2, color)
+    rust_debug:draw_line(x1, y1, x2, y2, color)
+end
+
+---Outline of a rectangle
+---@param x number
+---@param y number
+---@param w number
+---@param h number
+---@param color Color|nil White when nil
+function Debug:draw_rect(x, y, w, h, color)
+    rust_debug:draw_rect(x, y, w, h, color)
+end
+
+---@param text string
+---@param x number
+---@param y number
+---@param color Color|nil White when nil
+function Debug:draw_text(text, x, y, color)
+    rust_debug:draw_text(text, x, y, color)
+end
+
+---Show or hide the origin, axes, quad and name of every gameobject, like F3
+---@param visible boolean
+function Debug:set_overlay(visible)
+    rust_debug:set_overlay(visible)
+end
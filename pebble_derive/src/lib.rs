@@ -0,0 +1,237 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{quote, ToTokens};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Error, Expr, Fields, GenericParam, Lifetime, LitStr, Path, Type};
+
+/// Options of a field, read from `#[lua(...)]`
+struct FieldOptions {
+    rename: Option<String>,
+    default: Option<Expr>,
+    validate: Option<Path>,
+    skip: bool
+}
+
+impl FieldOptions {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let mut options = Self { rename: None, default: None, validate: None, skip: false };
+
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("lua")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    options.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("default") {
+                    options.default = Some(match meta.value() {
+                        Ok(value) => value.parse::<LitStr>()?.parse::<Expr>()?,
+                        Err(_) => parse_quote!(std::default::Default::default())
+                    });
+                } else if meta.path.is_ident("validate") {
+                    options.validate = Some(meta.value()?.parse::<LitStr>()?.parse::<Path>()?);
+                } else if meta.path.is_ident("skip") {
+                    options.skip = true;
+                } else {
+                    return Err(meta.error("unknown lua attribute, expected rename, default, validate or skip"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(options)
+    }
+}
+
+/// Options of the struct, read from `#[lua(...)]`
+struct StructOptions {
+    default: bool
+}
+
+impl StructOptions {
+    fn parse(input: &DeriveInput) -> syn::Result<Self> {
+        let mut options = Self { default: false };
+
+        for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("lua")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("default") {
+                    options.default = true;
+                } else {
+                    return Err(meta.error("unknown lua attribute, expected default"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(options)
+    }
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path.path.segments.last().map(|segment| segment.ident == "Option").unwrap_or(false),
+        _ => false
+    }
+}
+
+/// Generate `FromLua`, `ToLua`, `LuaComponent` and `LuaDefinition` for a struct with named fields.
+/// Every shared field need to implement `Clone` to be written back to lua,
+/// fields that are components themselves are written into the table they already have.
+///
+/// Field attributes :
+/// - `#[lua(rename = "name")]` use another name on the lua side
+/// - `#[lua(default)]` or `#[lua(default = "expr")]` value used when the field is nil
+/// - `#[lua(validate = "path::to::fn")]` call `fn(&T) -> Result<(), String>` after reading the field
+/// - `#[lua(skip)]` the field is not shared with lua and use `Default::default()`
+///
+/// Struct attributes :
+/// - `#[lua(default)]` nil fields without a default of their own take the value of the `Default` implementation of the struct
+///
+/// `Option<T>` fields are optional, a nil value become `None`.
+#[proc_macro_derive(LuaComponent, attributes(lua))]
+pub fn derive_lua_component(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(input) {
+        Ok(output) => output.into(),
+        Err(err) => err.to_compile_error().into()
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let ident = &input.ident;
+    let struct_options = StructOptions::parse(&input)?;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new_spanned(ident, "LuaComponent only support struct with named fields"))
+        },
+        _ => return Err(Error::new_spanned(ident, "LuaComponent only support struct"))
+    };
+
+    // The lua lifetime is the lifetime of the struct if it has one (struct holding lua values), otherwise a new one
+    let lifetimes: Vec<&Lifetime> = input.generics.lifetimes().map(|param| &param.lifetime).collect();
+
+    if lifetimes.len() > 1 {
+        return Err(Error::new_spanned(&input.generics, "LuaComponent support at most one lifetime"));
+    }
+
+    let lua_lifetime = lifetimes.first().map(|lifetime| (*lifetime).clone()).unwrap_or_else(|| Lifetime::new("'lua", Span::call_site()));
+
+    let mut impl_generics = input.generics.clone();
+
+    if lifetimes.is_empty() {
+        impl_generics.params.insert(0, GenericParam::Lifetime(syn::LifetimeParam::new(lua_lifetime.clone())));
+    }
+
+    let type_params: Vec<syn::Ident> = input.generics.type_params().map(|param| param.ident.clone()).collect();
+    let where_clause = impl_generics.make_where_clause();
+
+    for param in type_params.iter() {
+        where_clause.predicates.push(parse_quote!(#param: mlua::FromLua<#lua_lifetime> + mlua::ToLua<#lua_lifetime>));
+    }
+
+    let (impl_generics, _, where_clause) = impl_generics.split_for_impl();
+    let (definition_generics, ty_generics, definition_where_clause) = input.generics.split_for_impl();
+
+    let struct_name = ident.to_string();
+    let mut reads = Vec::new();
+    let mut writes = Vec::new();
+    let mut definitions = Vec::new();
+
+    for field in fields.iter() {
+        let options = FieldOptions::parse(field)?;
+        let name = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+
+        if options.skip {
+            reads.push(quote!(#name: std::default::Default::default()));
+            continue;
+        }
+
+        let lua_name = options.rename.clone().unwrap_or_else(|| name.to_string());
+        let rust_type = ty.to_token_stream().to_string();
+
+        let field_error = quote!(|err| mlua::Error::RuntimeError(format!("Cannot read field {}.{} : {}", #struct_name, #lua_name, err)));
+
+        let default = options.default.clone()
+            .or_else(|| (struct_options.default && !is_option(ty)).then(|| parse_quote!(defaults.#name.clone())));
+
+        let read = match &default {
+            Some(default) => quote!(table.get::<_, Option<#ty>>(#lua_name).map_err(#field_error)?.unwrap_or_else(|| #default)),
+            None => quote!(table.get::<_, #ty>(#lua_name).map_err(#field_error)?)
+        };
+
+        let read = match &options.validate {
+            Some(validate) => quote!({
+                let value : #ty = #read;
+                #validate(&value).map_err(|err| mlua::Error::RuntimeError(format!("Invalid field {}.{} : {}", #struct_name, #lua_name, err)))?;
+                value
+            }),
+            None => read
+        };
+
+        reads.push(quote!(#name: #read));
+        writes.push(quote!((&&&crate::lua::FieldWriter(&self.#name)).write_field(table, #lua_name)?;));
+
+        let rust_type = if default.is_some() && !is_option(ty) {
+            format!("Option<{}>", rust_type)
+        } else {
+            rust_type
+        };
+
+        definitions.push(quote!(crate::lua::LuaField { name: #lua_name, rust_type: #rust_type }));
+    }
+
+    let defaults = match struct_options.default {
+        true => quote!(let defaults = <Self as std::default::Default>::default();),
+        false => quote!()
+    };
+
+    Ok(quote! {
+        impl #impl_generics crate::lua::LuaComponent<#lua_lifetime> for #ident #ty_generics #where_clause {
+            fn from_lua_table(table: mlua::Table<#lua_lifetime>) -> mlua::Result<Self> {
+                #defaults
+
+                Ok(Self {
+                    #(#reads),*
+                })
+            }
+
+            fn write_to_table(&self, table: &mlua::Table<#lua_lifetime>) -> mlua::Result<()> {
+                #[allow(unused_imports)]
+                use crate::lua::{WriteComponentField, WriteOptionalComponentField, WriteValueField};
+
+                #(#writes)*
+                Ok(())
+            }
+        }
+
+        impl #impl_generics mlua::FromLua<#lua_lifetime> for #ident #ty_generics #where_clause {
+            fn from_lua(lua_value: mlua::Value<#lua_lifetime>, _: &#lua_lifetime mlua::Lua) -> mlua::Result<Self> {
+                match lua_value {
+                    mlua::Value::Table(table) => <Self as crate::lua::LuaComponent<#lua_lifetime>>::from_lua_table(table),
+                    mlua::Value::Error(err) => Err(err),
+                    other => Err(mlua::Error::RuntimeError(format!("Cannot convert {} to {}, only table are supported", other.type_name(), #struct_name)))
+                }
+            }
+        }
+
+        impl #impl_generics mlua::ToLua<#lua_lifetime> for #ident #ty_generics #where_clause {
+            fn to_lua(self, lua: &#lua_lifetime mlua::Lua) -> mlua::Result<mlua::Value<#lua_lifetime>> {
+                let table = lua.create_table()?;
+                <Self as crate::lua::LuaComponent<#lua_lifetime>>::write_to_table(&self, &table)?;
+                Ok(mlua::Value::Table(table))
+            }
+        }
+
+        impl #definition_generics crate::lua::LuaDefinition for #ident #ty_generics #definition_where_clause {
+            fn lua_name() -> &'static str {
+                #struct_name
+            }
+
+            fn lua_fields() -> Vec<crate::lua::LuaField> {
+                vec![#(#definitions),*]
+            }
+        }
+    })
+}
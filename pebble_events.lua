@@ -0,0 +1,78 @@
+---@meta
+
+Events = { listeners = {} }
+
+---Send an event to every gameobject, payload can only contain nil, boolean, number, string and table
+---@param name string
+---@param payload any
+function Events:emit(name, payload)
+    rust_events:emit(name, payload)
+end
+
+---@param name string
+---@param callback fun(payload: any, sender: string)
+function Events:on(name, callback)
+    if self.listeners[name] == nil then
+        self.listeners[name] = {}
+    end
+
+    table.insert(self.listeners[name], callback)
+end
+
+---Called by pebble when an event is received
+---@param name string
+---@param payload any
+---@param sender string
+function Events:dispatch(name, payload, sender)
+    local listeners = self.listeners[name]
+
+    if listeners == nil then
+        return
+    end
+
+    for _, callback in ipairs(listeners) do
+        callback(payload, sender)
+    end
+end
+
+Gameobjects = {}
+
+---Return a copy of the components of another gameobject, as they were after its last update
+---@param name string The script name of the gameobject, or `script#index` for spawned gameobjects
+---@return Array|nil
+function Gameobjects:find(name)
+    return rust_events:find(name)
+end
+
+---Create a gameobject running a script on the next update, properties are available as `Properties` in its script
+---@param script string
+---@param properties table|nil
+---@return string id The id of the gameobject, `script#index`
+function Gameobjects:spawn(script, properties)
+    return rust_events:spawn(script, properties)
+end
+
+---Remove a spawned gameobject on the next update
+---@param id string|nil The id returned by `spawn`, the gameobject of the script when nil
+function Gameobjects:despawn(id)
+    rust_events:despawn(id)
+end
+
+---@param name string The script name of the gameobject
+---@param c_type string
+---@return any
+function Gameobjects:get_component(name, c_type)
+    local components = self:find(name)
+
+    if components == nil then
+        return nil
+    end
+
+    for i = 0, components.count - 1 do
+        if components.data[i].c_type == c_type then
+            return components.data[i].data
+        end
+    end
+
+    return nil
+end
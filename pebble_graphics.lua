@@ -0,0 +1,94 @@
+---@meta
+
+---Immediate mode drawing, only available inside the `Draw` function of a script.
+---Coordinates are in the world, like `Transform.position`.
+Graphics = {}
+
+---Color of the next shapes, images and texts, from 0 to 255
+---@param r number
+---@param g number
+---@param b number
+---@param a number|nil
+function Graphics:set_color(r, g, b, a)
+    rust_graphics:set_color(r, g, b, a)
+end
+
+---Width of lines and of shapes drawn with the "line" mode
+---@param width number
+function Graphics:set_line_width(width)
+    rust_graphics:set_line_width(width)
+end
+
+---@param x number
+---@param y number
+---@param w number
+---@param h number
+---@param mode "fill"|"line"|nil
+function Graphics:rect(x, y, w, h, mode)
+    rust_graphics:rect(x, y, w, h, mode)
+end
+
+---@param x number
+---@param y number
+---@param radius number
+---@param mode "fill"|"line"|nil
+function Graphics:circle(x, y, radius, mode)
+    rust_graphics:circle(x, y, radius, mode)
+end
+
+---Line going through every point, written as x1, y1, x2, y2, ...
+---@param ... number
+function Graphics:line(...)
+    rust_graphics:line(...)
+end
+
+---Polygon with every point as corners, written as x1, y1, x2, y2, ...
+---@param mode "fill"|"line"|nil
+---@param ... number
+function Graphics:polygon(mode, ...)
+    rust_graphics:polygon(mode, ...)
+end
+
+---Draw a texture loaded with `Assets:add_image`, its top left corner at x, y
+---@param texture string
+---@param x number
+---@param y number
+---@param region Region|nil Part of the texture to draw, in pixels
+function Graphics:image(texture, x, y, region)
+    rust_graphics:image(texture, x, y, region)
+end
+
+---@param text string
+---@param x number
+---@param y number
+---@param font string|nil Font loaded with `Assets:add_font`
+---@param size number|nil
+function Graphics:text(text, x, y, font, size)
+    rust_graphics:text({ text = text, font = font, size = size }, x, y)
+end
+
+---Save the current transform, restored by `Graphics:pop`
+function Graphics:push()
+    rust_graphics:push()
+end
+
+function Graphics:pop()
+    rust_graphics:pop()
+end
+
+---@param x number
+---@param y number
+function Graphics:translate(x, y)
+    rust_graphics:translate(x, y)
+end
+
+---@param angle number In radians
+function Graphics:rotate(angle)
+    rust_graphics:rotate(angle)
+end
+
+---@param x number
+---@param y number|nil Same as x when nil
+function Graphics:scale(x, y)
+    rust_graphics:scale(x, y)
+end
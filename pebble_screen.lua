@@ -0,0 +1,28 @@
+---@meta
+
+---Screenshots and recording of the window, F12 saves a screenshot and F10 starts or stops a recording.
+---Paths are relative to the project, missing directories are created.
+Screen = {}
+
+---Save the next frame as a png
+---@param filename string
+function Screen:capture(filename)
+    rust_screen:capture(filename)
+end
+
+---Save every frame as directory/00000.png, directory/00001.png, ...
+---The game is updated at a fixed time step of 1 / fps while recording.
+---@param directory string
+---@param fps number|nil 60 when nil
+function Screen:start_recording(directory, fps)
+    rust_screen:start_recording(directory, fps)
+end
+
+function Screen:stop_recording()
+    rust_screen:stop_recording()
+end
+
+---@return boolean
+function Screen:is_recording()
+    return rust_screen:is_recording()
+end
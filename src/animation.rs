@@ -0,0 +1,263 @@
+use std::{collections::HashMap, error::Error};
+
+use serde::Deserialize;
+
+use crate::{assets::AssetsLoader, components::{Animator, Sprite}, gameobject::Gameobject};
+
+const DEFAULT_FRAME_DURATION: f32 = 0.1;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayMode {
+    Loop,
+    PingPong,
+    Once
+}
+
+#[derive(Deserialize)]
+struct ClipFrameDescriptor {
+    frame: String,
+    duration: Option<f32>,
+    tag: Option<String>
+}
+
+#[derive(Deserialize)]
+struct ClipDescriptor {
+    #[serde(default = "default_mode")]
+    mode: PlayMode,
+    /// Cycles played before the clip stops, forever when missing
+    repeat: Option<u32>,
+    /// Duration of the frames that does not have one
+    duration: Option<f32>,
+    frames: Vec<ClipFrameDescriptor>
+}
+
+/// Animation file, a json file listing clips made of frames of a sprite sheet
+#[derive(Deserialize)]
+struct AnimationDescriptor {
+    sheet: String,
+    clips: HashMap<String, ClipDescriptor>
+}
+
+fn default_mode() -> PlayMode {
+    PlayMode::Loop
+}
+
+#[derive(Clone, Debug)]
+pub struct ClipFrame {
+    pub frame: String,
+    pub duration: f32,
+    pub tag: Option<String>
+}
+
+#[derive(Clone, Debug)]
+pub struct Clip {
+    pub mode: PlayMode,
+    /// Cycles played before the clip stops, forever when none. A ping pong cycle goes forward and back
+    pub repeat: Option<u32>,
+    pub frames: Vec<ClipFrame>
+}
+
+/// Clips of a sprite sheet
+pub struct Animation {
+    sheet: String,
+    clips: HashMap<String, Clip>
+}
+
+/// Event sent to the `Animator` lua component
+#[derive(Clone, Debug, PartialEq)]
+pub enum AnimationEvent {
+    /// The clip reached its last frame (each cycle for looping clips)
+    End,
+    /// A frame with a tag started
+    Tag(String)
+}
+
+impl Animation {
+    pub fn new(sheet: &str, clips: HashMap<String, Clip>) -> Self {
+        Self { sheet: sheet.to_string(), clips }
+    }
+
+    pub fn from_json(file: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let descriptor : AnimationDescriptor = serde_json::from_slice(file)?;
+        let mut clips = HashMap::new();
+
+        for (name, clip) in descriptor.clips {
+            let default_duration = clip.duration.unwrap_or(DEFAULT_FRAME_DURATION);
+            let frames : Vec<ClipFrame> = clip.frames.into_iter().map(|frame| ClipFrame {
+                frame: frame.frame,
+                duration: frame.duration.unwrap_or(default_duration),
+                tag: frame.tag
+            }).collect();
+
+            // The animator would stay on a frame without duration forever
+            if let Some(frame) = frames.iter().find(|frame| frame.duration <= 0.0) {
+                return Err(format!("Frame {} of clip {} has a duration of {}, it need to be greater than 0", frame.frame, name, frame.duration).into());
+            }
+
+            clips.insert(name, Clip { mode: clip.mode, repeat: clip.repeat, frames });
+        }
+
+        Ok(Self::new(&descriptor.sheet, clips))
+    }
+
+    pub fn get_sheet(&self) -> &String {
+        &self.sheet
+    }
+
+    pub fn get_clip(&self, name: &str) -> Option<&Clip> {
+        self.clips.get(name)
+    }
+}
+
+impl Animator {
+    /// Count a cycle of the clip, stop it and return true when it was the last one
+    fn finish_cycle(&mut self, clip: &Clip) -> bool {
+        self.cycle += 1;
+
+        if clip.repeat.map(|repeat| self.cycle >= repeat).unwrap_or(false) {
+            self.playing = false;
+            self.time = 0.0;
+            return true;
+        }
+
+        false
+    }
+
+    /// Move the animator forward, return the events that happened during this update
+    pub fn advance(&mut self, clip: &Clip, delta: f32) -> Vec<AnimationEvent> {
+        let mut events = Vec::new();
+
+        if clip.frames.is_empty() || !self.playing {
+            return events;
+        }
+
+        let last = clip.frames.len() - 1;
+
+        self.frame = self.frame.min(last);
+
+        // The first frame is not reached by moving forward, its tag is sent when the clip starts
+        if !self.started {
+            self.started = true;
+
+            if let Some(tag) = &clip.frames[self.frame].tag {
+                events.push(AnimationEvent::Tag(tag.clone()));
+            }
+        }
+
+        self.time += delta * self.speed.max(0.0);
+
+        loop {
+            let duration = clip.frames[self.frame].duration;
+
+            if duration <= 0.0 || self.time < duration {
+                break;
+            }
+
+            self.time -= duration;
+
+            match clip.mode {
+                PlayMode::Loop => {
+                    if self.frame == last {
+                        events.push(AnimationEvent::End);
+
+                        if self.finish_cycle(clip) {
+                            break;
+                        }
+
+                        self.frame = 0;
+                    } else {
+                        self.frame += 1;
+                    }
+                },
+                PlayMode::Once => {
+                    if self.frame == last {
+                        events.push(AnimationEvent::End);
+                        self.playing = false;
+                        self.time = 0.0;
+                        break;
+                    }
+
+                    self.frame += 1;
+                },
+                PlayMode::PingPong => {
+                    if last == 0 {
+                        events.push(AnimationEvent::End);
+
+                        if self.finish_cycle(clip) {
+                            break;
+                        }
+
+                        continue;
+                    }
+
+                    if self.direction >= 0 && self.frame == last {
+                        self.direction = -1;
+                    } else if self.direction < 0 && self.frame == 0 {
+                        self.direction = 1;
+                    }
+
+                    self.frame = if self.direction >= 0 { self.frame + 1 } else { self.frame - 1 };
+
+                    if self.frame == 0 {
+                        events.push(AnimationEvent::End);
+                        self.finish_cycle(clip);
+                    }
+                }
+            }
+
+            if let Some(tag) = &clip.frames[self.frame].tag {
+                events.push(AnimationEvent::Tag(tag.clone()));
+            }
+
+            // A ping pong clip stops once back on its first frame
+            if !self.playing {
+                break;
+            }
+        }
+
+        events
+    }
+}
+
+/// System playing the `Animator` component and updating the `Sprite` component frame
+pub fn animate(go: &Gameobject, assets: &AssetsLoader, delta: f32) -> mlua::Result<()> {
+    let mut animator = match go.get_component::<Animator>()? {
+        Some(animator) => animator,
+        None => return Ok(())
+    };
+
+    let (animation_name, clip_name) = match (&animator.animation, &animator.clip) {
+        (Some(animation), Some(clip)) => (animation.clone(), clip.clone()),
+        _ => return Ok(())
+    };
+
+    // The animation can be missing for a frame, while the assets are loading
+    let animation = match assets.get_animation(&animation_name) {
+        Some(animation) => animation,
+        None => return Ok(())
+    };
+
+    let clip = animation.get_clip(&clip_name)
+        .ok_or_else(|| mlua::Error::RuntimeError(format!("Unknown clip {} in animation {}", clip_name, animation_name)))?;
+
+    let events = animator.advance(clip, delta);
+    go.set_component(&animator)?;
+
+    if let Some(mut sprite) = go.get_component::<Sprite>()? {
+        if let Some(frame) = clip.frames.get(animator.frame) {
+            sprite.sheet = Some(animation.get_sheet().clone());
+            sprite.frame = Some(frame.frame.clone());
+            go.set_component(&sprite)?;
+        }
+    }
+
+    for event in events {
+        match event {
+            AnimationEvent::End => go.call_component("Animator", "dispatch", ("end", clip_name.clone()))?,
+            AnimationEvent::Tag(tag) => go.call_component("Animator", "dispatch", ("tag", clip_name.clone(), tag))?
+        }
+    }
+
+    Ok(())
+}
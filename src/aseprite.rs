@@ -0,0 +1,298 @@
+use std::{collections::HashMap, error::Error, fmt};
+
+use ggez::{glam::Vec2, graphics::Rect};
+use serde::{Deserialize, Deserializer, de::{MapAccess, SeqAccess, Visitor}};
+
+use crate::{animation::{Animation, Clip, ClipFrame, PlayMode}, assets::resolve_path, sprite_sheet::{Frame, FrameTrim, SpriteSheet}};
+
+#[derive(Deserialize)]
+struct AsepriteRect {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32
+}
+
+#[derive(Deserialize)]
+struct AsepriteSize {
+    w: f32,
+    h: f32
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsepriteFrame {
+    frame: AsepriteRect,
+    /// Transparent borders are removed from the frame when the sprite is exported with trim
+    #[serde(default)]
+    trimmed: bool,
+    /// Position of the trimmed frame in the untrimmed one
+    sprite_source_size: Option<AsepriteRect>,
+    /// Size of the untrimmed frame
+    source_size: Option<AsepriteSize>,
+    /// Duration in milliseconds
+    duration: f32
+}
+
+/// Aseprite can export frames as an array or as a hash, the order of the hash is the order of the frames
+struct AsepriteFrames(Vec<AsepriteFrame>);
+
+struct AsepriteFramesVisitor;
+
+impl<'de> Visitor<'de> for AsepriteFramesVisitor {
+    type Value = AsepriteFrames;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array or a map of frames")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut frames = Vec::new();
+
+        while let Some(frame) = seq.next_element()? {
+            frames.push(frame);
+        }
+
+        Ok(AsepriteFrames(frames))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut frames = Vec::new();
+
+        while let Some((_, frame)) = map.next_entry::<String, AsepriteFrame>()? {
+            frames.push(frame);
+        }
+
+        Ok(AsepriteFrames(frames))
+    }
+}
+
+impl<'de> Deserialize<'de> for AsepriteFrames {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(AsepriteFramesVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+struct AsepriteTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default = "default_direction")]
+    direction: String,
+    /// Number of time the tag is played, infinite when missing or 0
+    repeat: Option<String>
+}
+
+#[derive(Deserialize)]
+struct AsepriteSliceKey {
+    frame: usize,
+    bounds: AsepriteRect
+}
+
+#[derive(Deserialize)]
+struct AsepriteSlice {
+    name: String,
+    keys: Vec<AsepriteSliceKey>
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsepriteMeta {
+    image: String,
+    #[serde(default)]
+    frame_tags: Vec<AsepriteTag>,
+    #[serde(default)]
+    slices: Vec<AsepriteSlice>
+}
+
+/// Json file exported by aseprite next to its png
+#[derive(Deserialize)]
+struct AsepriteFile {
+    frames: AsepriteFrames,
+    meta: AsepriteMeta
+}
+
+fn default_direction() -> String {
+    String::from("forward")
+}
+
+/// Sprite sheet and animation read from an aseprite export.
+///
+/// Frames are named by their index, slices become frames named after the slice (using their first key)
+/// and every tag become a clip of the animation.
+pub struct Aseprite {
+    pub sheet: SpriteSheet,
+    pub animation: Animation
+}
+
+impl Aseprite {
+    /// `name` is the name of the json file, used as the sprite sheet of the animation
+    pub fn from_json(name: &str, file: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let aseprite : AsepriteFile = serde_json::from_slice(file)?;
+        let frames = aseprite.frames.0;
+
+        let mut sheet_frames : Vec<Frame> = frames.iter().enumerate().map(|(index, frame)| Frame {
+            name: index.to_string(),
+            region: Rect::new(frame.frame.x, frame.frame.y, frame.frame.w, frame.frame.h),
+            trim: match (frame.trimmed, &frame.sprite_source_size, &frame.source_size) {
+                (true, Some(offset), Some(size)) => Some(FrameTrim { offset: Vec2::new(offset.x, offset.y), source_size: Vec2::new(size.w, size.h) }),
+                _ => None
+            }
+        }).collect();
+
+        for slice in aseprite.meta.slices.iter() {
+            let key = match slice.keys.first() {
+                Some(key) => key,
+                None => continue
+            };
+
+            let origin = frames.get(key.frame)
+                .ok_or_else(|| format!("Slice {} reference the frame {} that does not exist", slice.name, key.frame))?;
+            let trim = match (origin.trimmed, &origin.sprite_source_size) {
+                (true, Some(offset)) => Vec2::new(offset.x, offset.y),
+                _ => Vec2::ZERO
+            };
+
+            sheet_frames.push(Frame {
+                name: slice.name.clone(),
+                // Slice bounds are in the untrimmed frame
+                region: Rect::new(origin.frame.x + key.bounds.x - trim.x, origin.frame.y + key.bounds.y - trim.y, key.bounds.w, key.bounds.h),
+                trim: None
+            });
+        }
+
+        let mut clips = HashMap::new();
+
+        for tag in aseprite.meta.frame_tags.iter() {
+            if tag.from > tag.to || tag.to >= frames.len() {
+                return Err(format!("Tag {} reference frames {} to {} but there is {} frames", tag.name, tag.from, tag.to, frames.len()).into());
+            }
+
+            let mut indices : Vec<usize> = (tag.from..=tag.to).collect();
+
+            let mode = match tag.direction.as_str() {
+                "forward" => PlayMode::Loop,
+                "reverse" => {
+                    indices.reverse();
+                    PlayMode::Loop
+                },
+                "pingpong" => PlayMode::PingPong,
+                "pingpong_reverse" => {
+                    indices.reverse();
+                    PlayMode::PingPong
+                },
+                other => return Err(format!("Unknown direction {} for tag {}", other, tag.name).into())
+            };
+
+            let repeat = match tag.repeat.as_deref() {
+                Some(repeat) => repeat.parse::<u32>().map_err(|_| format!("Invalid repeat {} for tag {}", repeat, tag.name))?,
+                None => 0
+            };
+
+            clips.insert(tag.name.clone(), Clip {
+                mode,
+                repeat: (repeat > 0).then_some(repeat),
+                frames: indices.into_iter().map(|index| ClipFrame {
+                    frame: index.to_string(),
+                    duration: frames[index].duration / 1000.0,
+                    tag: None
+                }).collect()
+            });
+        }
+
+        Ok(Self {
+            // Aseprite writes the image relative to the json file
+            sheet: SpriteSheet::new(&resolve_path(name, &aseprite.meta.image), sheet_frames),
+            animation: Animation::new(name, clips)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = r#"{
+        "frames": {
+            "hero 0.aseprite": { "frame": { "x": 0, "y": 0, "w": 16, "h": 16 }, "duration": 100 },
+            "hero 1.aseprite": { "frame": { "x": 16, "y": 0, "w": 16, "h": 16 }, "duration": 150 },
+            "hero 2.aseprite": {
+                "frame": { "x": 32, "y": 0, "w": 8, "h": 12 }, "trimmed": true,
+                "spriteSourceSize": { "x": 6, "y": 2, "w": 8, "h": 12 }, "sourceSize": { "w": 16, "h": 16 },
+                "duration": 200
+            }
+        },
+        "meta": {
+            "image": "hero.png",
+            "frameTags": [
+                { "name": "idle", "from": 0, "to": 1, "direction": "forward" },
+                { "name": "back", "from": 0, "to": 2, "direction": "reverse", "repeat": "1" },
+                { "name": "bounce", "from": 1, "to": 2, "direction": "pingpong", "repeat": "3" }
+            ],
+            "slices": [
+                { "name": "hitbox", "keys": [{ "frame": 2, "bounds": { "x": 5, "y": 3, "w": 4, "h": 6 } }] }
+            ]
+        }
+    }"#;
+
+    fn frames(clip: &Clip) -> Vec<&str> {
+        clip.frames.iter().map(|frame| frame.frame.as_str()).collect()
+    }
+
+    #[test]
+    fn frames_and_slices() {
+        let aseprite = Aseprite::from_json("sprites/hero.json", EXPORT.as_bytes()).unwrap();
+
+        assert_eq!(aseprite.sheet.get_image(), "sprites/hero.png");
+        assert_eq!(aseprite.sheet.get_frame("1").unwrap().region, Rect::new(16.0, 0.0, 16.0, 16.0));
+        assert!(aseprite.sheet.get_frame("0").unwrap().trim.is_none());
+        assert_eq!(aseprite.sheet.get_frame("hitbox").unwrap().region, Rect::new(31.0, 1.0, 4.0, 6.0));
+    }
+
+    #[test]
+    fn tags_are_clips() {
+        let aseprite = Aseprite::from_json("hero.json", EXPORT.as_bytes()).unwrap();
+        assert_eq!(aseprite.animation.get_sheet(), "hero.json");
+
+        let idle = aseprite.animation.get_clip("idle").unwrap();
+        assert_eq!((idle.mode, idle.repeat), (PlayMode::Loop, None));
+        assert_eq!(frames(idle), vec!["0", "1"]);
+        assert_eq!(idle.frames[1].duration, 0.15);
+
+        let back = aseprite.animation.get_clip("back").unwrap();
+        assert_eq!((back.mode, back.repeat), (PlayMode::Loop, Some(1)));
+        assert_eq!(frames(back), vec!["2", "1", "0"]);
+
+        let bounce = aseprite.animation.get_clip("bounce").unwrap();
+        assert_eq!((bounce.mode, bounce.repeat), (PlayMode::PingPong, Some(3)));
+        assert_eq!(frames(bounce), vec!["1", "2"]);
+    }
+
+    #[test]
+    fn trimmed_pivot() {
+        let aseprite = Aseprite::from_json("hero.json", EXPORT.as_bytes()).unwrap();
+        let frame = aseprite.sheet.get_frame("2").unwrap();
+
+        let pivot = |pivot: Vec2, flip_x: bool, flip_y: bool| frame.pivot(pivot, flip_x, flip_y);
+
+        // The center of the untrimmed frame is at (2, 6) in the trimmed one
+        assert!(pivot(Vec2::new(0.5, 0.5), false, false).abs_diff_eq(Vec2::new(0.25, 0.5), 1e-6));
+        assert!(pivot(Vec2::ZERO, false, false).abs_diff_eq(Vec2::new(-0.75, -2.0 / 12.0), 1e-6));
+        // Flipped, the trimmed region is 2 pixels from the left of the untrimmed frame
+        assert!(pivot(Vec2::ZERO, true, false).abs_diff_eq(Vec2::new(-0.25, -2.0 / 12.0), 1e-6));
+        assert!(pivot(Vec2::new(0.0, 1.0), false, true).abs_diff_eq(Vec2::new(-0.75, 14.0 / 12.0), 1e-6));
+    }
+
+    #[test]
+    fn invalid_tags() {
+        let export = EXPORT.replace(r#""to": 1, "direction": "forward""#, r#""to": 3, "direction": "forward""#);
+        assert!(Aseprite::from_json("hero.json", export.as_bytes()).is_err());
+
+        let export = EXPORT.replace(r#""direction": "forward""#, r#""direction": "sideways""#);
+        assert!(Aseprite::from_json("hero.json", export.as_bytes()).is_err());
+
+        let export = EXPORT.replace(r#""repeat": "3""#, r#""repeat": "many""#);
+        assert!(Aseprite::from_json("hero.json", export.as_bytes()).is_err());
+    }
+}
@@ -1,21 +1,38 @@
-use std::{collections::HashMap, error::Error};
+use std::{collections::{HashMap, HashSet}, error::Error, path::{Component, Path, PathBuf}, sync::mpsc::{channel, Receiver}};
 
-use crate::user_data;
+use notify::{Watcher, EventKind, event::{AccessKind, AccessMode}};
 
-user_data!(
-    #[derive(Clone, Debug)]
-    pub struct LuaAsset<'a> {
-        filename: String,
-        file_type: String
-    }
-);
+use crate::{lua::LuaComponent, sprite_sheet::SpriteSheet, animation::Animation, aseprite::Aseprite, tilemap::TiledMap, bitmap_font::BitmapFont, shader::ShaderAsset, components::ParticleEmitter};
+
+#[derive(Clone, Debug, LuaComponent)]
+pub struct LuaAsset {
+    filename: String,
+    file_type: String
+}
 
-impl<'a> LuaAsset<'a> {
+impl LuaAsset {
     pub fn get_filename(&self) -> &String {
         &self.filename
     }
 }
 
+/// Resolve a path written in an asset file, relative to this file, to a path relative to the assets folder
+pub fn resolve_path(base: &str, relative: &str) -> String {
+    let mut path = PathBuf::new();
+
+    for component in Path::new(base).parent().unwrap_or(Path::new("")).join(relative).components() {
+        match component {
+            Component::ParentDir => {
+                path.pop();
+            },
+            Component::Normal(name) => path.push(name),
+            _ => {}
+        }
+    }
+
+    path.to_string_lossy().replace('\\', "/")
+}
+
 struct AssetStorage<T> {
     storage: HashMap<String, T>
 }
@@ -34,18 +51,65 @@ impl<T> AssetStorage<T> {
     pub fn get(&self, name: &str) -> Option<&T> {
         self.storage.get(&name.to_string())
     }
+
+    pub fn remove(&mut self, name: &str) {
+        self.storage.remove(name);
+    }
 }
 
 /// Load file receive from lua script
 pub struct AssetsLoader {
     storage: AssetStorage<Vec<u8>>,
     textures: AssetStorage<ggez::graphics::Image>,
-    ui_textures: AssetStorage<egui_extras::RetainedImage>
+    sprite_sheets: AssetStorage<SpriteSheet>,
+    animations: AssetStorage<Animation>,
+    tilemaps: AssetStorage<TiledMap>,
+    ui_textures: AssetStorage<egui_extras::RetainedImage>,
+    /// Fonts registered in the ggez context, named after their file
+    fonts: HashSet<String>,
+    bitmap_fonts: AssetStorage<BitmapFont>,
+    shaders: AssetStorage<ShaderAsset>,
+    particles: AssetStorage<ParticleEmitter>,
+    /// Images of the `RenderTarget` components, drawn every frame
+    render_targets: AssetStorage<ggez::graphics::Image>,
+    /// Aseprite json files and their image, reloaded when aseprite export them again
+    aseprite_files: HashMap<String, String>,
+    /// Incremented each time assets are reloaded, anything holding a loaded asset must drop it
+    generation: u64,
+    receiver: Receiver<Result<notify::Event, notify::Error>>,
+    _watcher: Option<notify::RecommendedWatcher>
 }
 
 impl AssetsLoader {
     pub fn new() -> Self {
-        Self { storage: AssetStorage::new(), textures: AssetStorage::new(), ui_textures: AssetStorage::new() }
+        let (sender, receiver) = channel();
+
+        let watcher = notify::recommended_watcher(sender).and_then(|mut watcher| {
+            watcher.watch(Path::new("./assets/"), notify::RecursiveMode::Recursive)?;
+            Ok(watcher)
+        });
+
+        if let Err(err) = &watcher {
+            warn!("Assets hot reload is disabled : {}", err);
+        }
+
+        Self {
+            storage: AssetStorage::new(),
+            textures: AssetStorage::new(),
+            sprite_sheets: AssetStorage::new(),
+            animations: AssetStorage::new(),
+            tilemaps: AssetStorage::new(),
+            ui_textures: AssetStorage::new(),
+            fonts: HashSet::new(),
+            bitmap_fonts: AssetStorage::new(),
+            shaders: AssetStorage::new(),
+            particles: AssetStorage::new(),
+            render_targets: AssetStorage::new(),
+            aseprite_files: HashMap::new(),
+            generation: 0,
+            receiver,
+            _watcher: watcher.ok()
+        }
     }
 
     pub fn load_file(&mut self, filename: &str) -> Result<Vec<u8>, Box<dyn Error>> {
@@ -63,6 +127,203 @@ impl AssetsLoader {
         Ok(self.textures.get(filename).unwrap())
     }
 
+    pub fn load_sprite_sheet(&mut self, filename: &str, ctx: &ggez::Context) -> Result<&SpriteSheet, Box<dyn Error>> {
+        let file = self.load_file(filename)?;
+        let image = self.load_texture(&SpriteSheet::read_image(filename, &file)?, ctx)?;
+        let sheet = SpriteSheet::from_json(filename, &file, image.width(), image.height())?;
+
+        self.sprite_sheets.add(filename, sheet);
+        debug!("Successfully loaded sprite sheet {}", filename);
+
+        Ok(self.sprite_sheets.get(filename).unwrap())
+    }
+
+    pub fn load_animation(&mut self, filename: &str, ctx: &ggez::Context) -> Result<&Animation, Box<dyn Error>> {
+        let file = self.load_file(filename)?;
+        let animation = Animation::from_json(&file)?;
+
+        self.load_sprite_sheet(animation.get_sheet(), ctx)?;
+        self.animations.add(filename, animation);
+        debug!("Successfully loaded animation {}", filename);
+
+        Ok(self.animations.get(filename).unwrap())
+    }
+
+    /// Load an aseprite json export, its frames are stored as a sprite sheet and its tags as an animation both named after the json file
+    pub fn load_aseprite(&mut self, filename: &str, ctx: &ggez::Context) -> Result<&SpriteSheet, Box<dyn Error>> {
+        let file = self.load_file(filename)?;
+        let aseprite = Aseprite::from_json(filename, &file)?;
+        let image = aseprite.sheet.get_image().clone();
+
+        self.load_texture(&image, ctx)?;
+        self.sprite_sheets.add(filename, aseprite.sheet);
+        self.animations.add(filename, aseprite.animation);
+        self.aseprite_files.insert(filename.to_string(), image);
+        debug!("Successfully loaded aseprite file {}", filename);
+
+        Ok(self.sprite_sheets.get(filename).unwrap())
+    }
+
+    /// Load a tiled map (.tmx, .tmj or .json), its external tilesets and their images
+    pub fn load_tilemap(&mut self, filename: &str, ctx: &ggez::Context) -> Result<&TiledMap, Box<dyn Error>> {
+        let file = self.load_file(filename)?;
+
+        let mut map = if filename.ends_with(".tmx") {
+            TiledMap::from_tmx(filename, &file, &mut |file| self.load_file(file))?
+        } else {
+            TiledMap::from_json(filename, &file, &mut |file| self.load_file(file))?
+        };
+
+        for tileset in map.tilesets.iter() {
+            self.load_texture(&tileset.image, ctx)?;
+        }
+
+        map.build_meshes(ctx, |image| self.get_texture(image).cloned())?;
+        self.tilemaps.add(filename, map);
+        debug!("Successfully loaded tilemap {}", filename);
+
+        Ok(self.tilemaps.get(filename).unwrap())
+    }
+
+    /// Load a ttf or otf font, or a bitmap font (.fnt) and its pages, texts use it with its filename
+    pub fn load_font(&mut self, filename: &str, ctx: &mut ggez::Context) -> Result<(), Box<dyn Error>> {
+        if self.has_font(filename) {
+            return Ok(());
+        }
+
+        if filename.ends_with(".fnt") {
+            return self.load_bitmap_font(filename, ctx).map(|_| ());
+        }
+
+        let file = self.load_file(filename)?;
+        ctx.gfx.add_font(filename, ggez::graphics::FontData::from_vec(file)?);
+        self.fonts.insert(filename.to_string());
+        debug!("Successfully loaded font {}", filename);
+
+        Ok(())
+    }
+
+    pub fn load_bitmap_font(&mut self, filename: &str, ctx: &ggez::Context) -> Result<&BitmapFont, Box<dyn Error>> {
+        let file = self.load_file(filename)?;
+        let font = BitmapFont::from_fnt(filename, &file)?;
+
+        for page in font.get_pages().iter() {
+            self.load_texture(page, ctx)?;
+        }
+
+        self.bitmap_fonts.add(filename, font);
+        debug!("Successfully loaded bitmap font {}", filename);
+
+        Ok(self.bitmap_fonts.get(filename).unwrap())
+    }
+
+    /// Load a wgsl fragment shader, materials use it with its filename
+    pub fn load_shader(&mut self, filename: &str, ctx: &ggez::Context) -> Result<&ShaderAsset, Box<dyn Error>> {
+        let file = self.load_file(filename)?;
+        let shader = ShaderAsset::from_wgsl(ctx, filename, &String::from_utf8(file)?)?;
+
+        self.shaders.add(filename, shader);
+        debug!("Successfully loaded shader {}", filename);
+
+        Ok(self.shaders.get(filename).unwrap())
+    }
+
+    /// Load the settings of a particle emitter from a json file
+    pub fn load_particles(&mut self, filename: &str) -> Result<&ParticleEmitter, Box<dyn Error>> {
+        let file = self.load_file(filename)?;
+
+        self.particles.add(filename, ParticleEmitter::from_json(&file)?);
+        debug!("Successfully loaded particles {}", filename);
+
+        Ok(self.particles.get(filename).unwrap())
+    }
+
+    /// Image of a render target, created again when its size change
+    pub fn load_render_target(&mut self, name: &str, width: u32, height: u32, ctx: &ggez::Context) -> ggez::graphics::Image {
+        let resized = self.render_targets.get(name).is_none_or(|image| image.width() != width || image.height() != height);
+
+        if resized {
+            self.render_targets.remove(name);
+            self.render_targets.add(name, ggez::graphics::Image::new_canvas_image(ctx, ctx.gfx.surface_format(), width, height, 1));
+            // Instance arrays keep the previous image
+            self.generation += 1;
+            debug!("Successfully created render target {} ({}x{})", name, width, height);
+        }
+
+        self.render_targets.get(name).unwrap().clone()
+    }
+
+    /// Reload the aseprite files and the shaders that changed on disk since the last call
+    pub fn reload_changed(&mut self, ctx: &ggez::Context) {
+        let mut changed = HashSet::new();
+        let mut changed_shaders = HashSet::new();
+
+        while let Ok(event) = self.receiver.try_recv() {
+            let event = match event {
+                Ok(event) => event,
+                Err(err) => {
+                    error!("Error when watching assets : {}", err);
+                    continue;
+                }
+            };
+
+            if !matches!(event.kind, EventKind::Access(AccessKind::Close(AccessMode::Write)) | EventKind::Create(_)) {
+                continue;
+            }
+
+            for path in event.paths.iter() {
+                for (filename, image) in self.aseprite_files.iter() {
+                    if path.ends_with(filename) || path.ends_with(image) {
+                        changed.insert(filename.clone());
+                    }
+                }
+
+                for filename in self.shaders.storage.keys() {
+                    if path.ends_with(filename) {
+                        changed_shaders.insert(filename.clone());
+                    }
+                }
+            }
+        }
+
+        if !changed.is_empty() || !changed_shaders.is_empty() {
+            self.generation += 1;
+        }
+
+        for filename in changed {
+            if let Some(image) = self.aseprite_files.get(&filename).cloned() {
+                self.storage.remove(&filename);
+                self.storage.remove(&image);
+                self.textures.remove(&image);
+                self.sprite_sheets.remove(&filename);
+                self.animations.remove(&filename);
+            }
+
+            match self.load_aseprite(&filename, ctx) {
+                Ok(_) => info!("Reloaded aseprite file {}", filename),
+                Err(err) => error!("Error when reloading aseprite file {} : {}", filename, err),
+            }
+        }
+
+        for filename in changed_shaders {
+            self.storage.remove(&filename);
+
+            // The previous shader is kept until the new one compiles
+            let shader = self.load_file(&filename)
+                .and_then(|file| Ok(String::from_utf8(file)?))
+                .and_then(|source| ShaderAsset::from_wgsl(ctx, &filename, &source));
+
+            match shader {
+                Ok(shader) => {
+                    self.shaders.remove(&filename);
+                    self.shaders.add(&filename, shader);
+                    info!("Reloaded shader {}", filename);
+                },
+                Err(err) => error!("Error when reloading shader {} : {}", filename, err),
+            }
+        }
+    }
+
     pub fn load_ui_texture(&mut self, filename: &str) -> Result<&egui_extras::RetainedImage, Box<dyn Error>> {
         let file = self.load_file(filename)?;
         self.ui_textures.add(filename, egui_extras::RetainedImage::from_image_bytes(filename, &file)?);
@@ -71,12 +332,45 @@ impl AssetsLoader {
         Ok(self.ui_textures.get(filename).unwrap())
     }
 
+    pub fn get_generation(&self) -> u64 {
+        self.generation
+    }
+
     pub fn get_file(&self, filename: &str) -> Option<&Vec<u8>> {
         self.storage.get(filename)
     }
 
+    /// Texture loaded from a file, or the image of a render target
     pub fn get_texture(&self, filename: &str) -> Option<&ggez::graphics::Image> {
-        self.textures.get(filename)
+        self.textures.get(filename).or_else(|| self.render_targets.get(filename))
+    }
+
+    pub fn get_sprite_sheet(&self, filename: &str) -> Option<&SpriteSheet> {
+        self.sprite_sheets.get(filename)
+    }
+
+    pub fn get_animation(&self, filename: &str) -> Option<&Animation> {
+        self.animations.get(filename)
+    }
+
+    pub fn get_tilemap(&self, filename: &str) -> Option<&TiledMap> {
+        self.tilemaps.get(filename)
+    }
+
+    pub fn has_font(&self, filename: &str) -> bool {
+        self.fonts.contains(filename) || self.bitmap_fonts.get(filename).is_some()
+    }
+
+    pub fn get_bitmap_font(&self, filename: &str) -> Option<&BitmapFont> {
+        self.bitmap_fonts.get(filename)
+    }
+
+    pub fn get_shader(&self, filename: &str) -> Option<&ShaderAsset> {
+        self.shaders.get(filename)
+    }
+
+    pub fn get_particles(&self, filename: &str) -> Option<&ParticleEmitter> {
+        self.particles.get(filename)
     }
 
     pub fn get_ui_texture(&self, filename: &str) -> Option<&egui_extras::RetainedImage> {
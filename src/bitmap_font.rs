@@ -0,0 +1,349 @@
+use std::{collections::HashMap, error::Error};
+
+use ggez::{glam::{Mat4, Vec2, Vec3}, graphics::Rect};
+
+use crate::{assets::resolve_path, components::Text, tilemap::XmlElement};
+
+/// Glyph of a bitmap font, in pixels
+#[derive(Clone, Debug)]
+pub struct Glyph {
+    pub region: Rect,
+    pub offset: Vec2,
+    pub advance: f32,
+    pub page: usize
+}
+
+/// Glyph placed by `BitmapFont::layout`, relative to the origin of the text
+#[derive(Clone, Debug)]
+pub struct PlacedGlyph {
+    pub page: usize,
+    pub region: Rect,
+    pub position: Vec2
+}
+
+/// BMFont descriptor (`.fnt`, text or xml) and the images of its pages
+pub struct BitmapFont {
+    line_height: f32,
+    /// Images relative to the assets folder
+    pages: Vec<String>,
+    glyphs: HashMap<char, Glyph>,
+    kernings: HashMap<(char, char), f32>
+}
+
+/// Split a line of the text format, like `char id=32 x=0 y=0`, quoted values can contain spaces
+fn parse_line(line: &str) -> Option<XmlElement> {
+    let mut tokens : Vec<String> = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            },
+            c => token.push(c)
+        }
+    }
+
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    let mut tokens = tokens.into_iter();
+    let name = tokens.next()?;
+
+    Some(XmlElement {
+        name,
+        attributes: tokens.filter_map(|token| token.split_once('=').map(|(key, value)| (key.to_string(), value.to_string()))).collect(),
+        children: Vec::new(),
+        text: String::new()
+    })
+}
+
+/// Every element of the xml format, in document order
+fn flatten(mut element: XmlElement, elements: &mut Vec<XmlElement>) {
+    let children = std::mem::take(&mut element.children);
+
+    elements.push(element);
+
+    for child in children {
+        flatten(child, elements);
+    }
+}
+
+fn to_char(id: u32, element: &XmlElement) -> Result<char, Box<dyn Error>> {
+    char::from_u32(id).ok_or_else(|| format!("Invalid character id {} on <{}>", id, element.name).into())
+}
+
+impl BitmapFont {
+    /// `filename` is the path of the descriptor, the images of the pages are relative to it
+    pub fn from_fnt(filename: &str, file: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let content = String::from_utf8_lossy(file);
+        let mut elements = Vec::new();
+
+        if content.trim_start().starts_with('<') {
+            flatten(XmlElement::parse(file)?, &mut elements);
+        } else {
+            elements.extend(content.lines().filter_map(parse_line));
+        }
+
+        let mut font = Self { line_height: 0.0, pages: Vec::new(), glyphs: HashMap::new(), kernings: HashMap::new() };
+
+        for element in elements.iter() {
+            match element.name.as_str() {
+                "common" => font.line_height = element.attribute("lineHeight")?,
+                "page" => {
+                    let id : usize = element.attribute("id")?;
+                    let file : String = element.attribute("file")?;
+
+                    if font.pages.len() <= id {
+                        font.pages.resize(id + 1, String::new());
+                    }
+
+                    font.pages[id] = resolve_path(filename, &file);
+                },
+                "char" => {
+                    let id = to_char(element.attribute("id")?, element)?;
+
+                    font.glyphs.insert(id, Glyph {
+                        region: Rect::new(element.attribute("x")?, element.attribute("y")?, element.attribute("width")?, element.attribute("height")?),
+                        offset: Vec2::new(element.attribute_or("xoffset", 0.0)?, element.attribute_or("yoffset", 0.0)?),
+                        advance: element.attribute("xadvance")?,
+                        page: element.attribute_or("page", 0)?
+                    });
+                },
+                "kerning" => {
+                    let first = to_char(element.attribute("first")?, element)?;
+                    let second = to_char(element.attribute("second")?, element)?;
+
+                    font.kernings.insert((first, second), element.attribute("amount")?);
+                },
+                _ => {}
+            }
+        }
+
+        if font.pages.is_empty() || font.pages.iter().any(|page| page.is_empty()) {
+            return Err(format!("Bitmap font {} has missing pages", filename).into());
+        }
+
+        if let Some(glyph) = font.glyphs.values().find(|glyph| glyph.page >= font.pages.len()) {
+            return Err(format!("Bitmap font {} has a glyph on the page {} that does not exist", filename, glyph.page).into());
+        }
+
+        if font.line_height <= 0.0 {
+            font.line_height = font.glyphs.values().map(|glyph| glyph.region.h + glyph.offset.y).fold(0.0, f32::max);
+        }
+
+        Ok(font)
+    }
+
+    pub fn get_pages(&self) -> &Vec<String> {
+        &self.pages
+    }
+
+    /// Integer scale used to draw the font at the size of a text, bitmap fonts are only scaled by whole pixels
+    pub fn scale(&self, size: f32) -> f32 {
+        (size / self.line_height).round().max(1.0)
+    }
+
+    fn line_width(&self, line: &[char]) -> f32 {
+        let mut width = 0.0;
+
+        for (index, c) in line.iter().enumerate() {
+            if let Some(glyph) = self.glyphs.get(c) {
+                width += glyph.advance;
+            }
+
+            if let Some(next) = line.get(index + 1) {
+                width += self.kernings.get(&(*c, *next)).copied().unwrap_or(0.0);
+            }
+        }
+
+        width
+    }
+
+    /// Split the text in lines, wrapping words at `wrap_width` (in font pixels)
+    fn lines(&self, text: &str, wrap_width: Option<f32>) -> Vec<Vec<char>> {
+        let mut lines = Vec::new();
+
+        for paragraph in text.split('\n') {
+            let mut line : Vec<char> = Vec::new();
+
+            for word in paragraph.split(' ') {
+                let mut candidate = line.clone();
+
+                if !candidate.is_empty() {
+                    candidate.push(' ');
+                }
+
+                candidate.extend(word.chars());
+
+                match wrap_width {
+                    Some(width) if !line.is_empty() && self.line_width(&candidate) > width => {
+                        lines.push(std::mem::replace(&mut line, word.chars().collect()));
+                    },
+                    _ => line = candidate
+                }
+            }
+
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    /// Place the glyphs of a text, in font pixels. The origin is the top of the first line,
+    /// on its left, center or right depending on the alignment (like ttf texts)
+    pub fn layout(&self, text: &Text, scale: f32) -> Vec<PlacedGlyph> {
+        let mut glyphs = Vec::new();
+
+        for (row, line) in self.lines(&text.text, text.wrap_width.map(|width| width / scale)).iter().enumerate() {
+            let width = self.line_width(line);
+
+            let mut x = match text.align.as_str() {
+                "center" => (-width / 2.0).round(),
+                "right" => -width,
+                _ => 0.0
+            };
+
+            let y = row as f32 * self.line_height;
+
+            for (index, c) in line.iter().enumerate() {
+                if let Some(glyph) = self.glyphs.get(c) {
+                    if glyph.region.w > 0.0 && glyph.region.h > 0.0 {
+                        glyphs.push(PlacedGlyph {
+                            page: glyph.page,
+                            region: glyph.region,
+                            position: Vec2::new(x + glyph.offset.x, y + glyph.offset.y)
+                        });
+                    }
+
+                    x += glyph.advance;
+                }
+
+                if let Some(next) = line.get(index + 1) {
+                    x += self.kernings.get(&(*c, *next)).copied().unwrap_or(0.0);
+                }
+            }
+        }
+
+        glyphs
+    }
+}
+
+/// Transform of a glyph quad, `origin` is the transform of the text and `scale` the scale of the font
+pub fn glyph_transform(origin: Mat4, scale: f32, glyph: &PlacedGlyph) -> Mat4 {
+    origin
+        * Mat4::from_scale(Vec3::new(scale, scale, 1.0))
+        * Mat4::from_translation(glyph.position.extend(0.0))
+        * Mat4::from_scale(Vec3::new(glyph.region.w, glyph.region.h, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::Color;
+
+    const TEXT_FONT: &str = r#"info face="Pixel" size=8
+common lineHeight=10 base=8 scaleW=64 scaleH=64 pages=1
+page id=0 file="pixel font.png"
+chars count=3
+char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=3 page=0
+char id=65 x=0 y=0 width=5 height=7 xoffset=0 yoffset=1 xadvance=6 page=0
+char id=86 x=5 y=0 width=5 height=7 xoffset=0 yoffset=1 xadvance=6 page=0
+kernings count=1
+kerning first=65 second=86 amount=-1
+"#;
+
+    const XML_FONT: &str = r#"<?xml version="1.0"?>
+<font>
+    <common lineHeight="12" base="10" scaleW="64" scaleH="64" pages="2"/>
+    <pages>
+        <page id="1" file="font_1.png"/>
+        <page id="0" file="font_0.png"/>
+    </pages>
+    <chars count="2">
+        <char id="65" x="0" y="0" width="5" height="7" xoffset="1" yoffset="2" xadvance="6" page="0"/>
+        <char id="66" x="0" y="0" width="5" height="7" xadvance="7" page="1"/>
+    </chars>
+    <kernings count="1">
+        <kerning first="65" second="66" amount="2"/>
+    </kernings>
+</font>"#;
+
+    fn text(text: &str, align: &str, wrap_width: Option<f32>) -> Text {
+        Text {
+            text: text.to_string(),
+            font: None,
+            size: 10.0,
+            color: Color { r: 255.0, g: 255.0, b: 255.0, a: 255.0 },
+            align: align.to_string(),
+            wrap_width
+        }
+    }
+
+    fn positions(glyphs: &[PlacedGlyph]) -> Vec<(f32, f32)> {
+        glyphs.iter().map(|glyph| (glyph.position.x, glyph.position.y)).collect()
+    }
+
+    #[test]
+    fn text_format() {
+        let font = BitmapFont::from_fnt("fonts/pixel.fnt", TEXT_FONT.as_bytes()).unwrap();
+
+        assert_eq!(font.line_height, 10.0);
+        assert_eq!(font.get_pages(), &vec![String::from("fonts/pixel font.png")]);
+        assert_eq!(font.glyphs[&'A'].region, Rect::new(0.0, 0.0, 5.0, 7.0));
+        assert_eq!(font.glyphs[&'A'].offset, Vec2::new(0.0, 1.0));
+        assert_eq!(font.kernings[&('A', 'V')], -1.0);
+    }
+
+    #[test]
+    fn xml_format() {
+        let font = BitmapFont::from_fnt("fonts/big.fnt", XML_FONT.as_bytes()).unwrap();
+
+        assert_eq!(font.line_height, 12.0);
+        assert_eq!(font.get_pages(), &vec![String::from("fonts/font_0.png"), String::from("fonts/font_1.png")]);
+        assert_eq!(font.glyphs[&'B'].page, 1);
+        assert_eq!(font.glyphs[&'B'].offset, Vec2::ZERO);
+        assert_eq!(font.kernings[&('A', 'B')], 2.0);
+    }
+
+    #[test]
+    fn missing_page() {
+        let file = "common lineHeight=10\npage id=1 file=\"font_1.png\"\nchar id=65 x=0 y=0 width=5 height=7 xadvance=6 page=1\n";
+
+        assert!(BitmapFont::from_fnt("font.fnt", file.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn kerning() {
+        let font = BitmapFont::from_fnt("pixel.fnt", TEXT_FONT.as_bytes()).unwrap();
+
+        assert_eq!(positions(&font.layout(&text("AV", "left", None), 1.0)), vec![(0.0, 1.0), (5.0, 1.0)]);
+        assert_eq!(positions(&font.layout(&text("VA", "left", None), 1.0)), vec![(0.0, 1.0), (6.0, 1.0)]);
+        assert_eq!(font.line_width(&['A', 'V']), 11.0);
+    }
+
+    #[test]
+    fn alignment() {
+        let font = BitmapFont::from_fnt("pixel.fnt", TEXT_FONT.as_bytes()).unwrap();
+
+        assert_eq!(positions(&font.layout(&text("A", "center", None), 1.0)), vec![(-3.0, 1.0)]);
+        assert_eq!(positions(&font.layout(&text("A", "right", None), 1.0)), vec![(-6.0, 1.0)]);
+    }
+
+    #[test]
+    fn wrapping() {
+        let font = BitmapFont::from_fnt("pixel.fnt", TEXT_FONT.as_bytes()).unwrap();
+
+        // The wrap width is in screen pixels, the font is drawn twice bigger
+        let glyphs = font.layout(&text("A A\nV", "left", Some(20.0)), 2.0);
+
+        assert_eq!(positions(&glyphs), vec![(0.0, 1.0), (0.0, 11.0), (0.0, 21.0)]);
+        assert_eq!(font.scale(20.0), 2.0);
+        assert_eq!(font.scale(4.0), 1.0);
+    }
+}
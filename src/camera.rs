@@ -0,0 +1,135 @@
+use ggez::{glam::{Mat4, Vec2, Vec3}, graphics::Rect};
+use mlua::UserData;
+
+use crate::{assets::AssetsLoader, components::{Camera, Transform, Vector}, gameobject::Gameobject, lua::{Script, write_component}};
+
+/// Frequency of the screen shake, in radians per second
+const SHAKE_FREQUENCY: Vec2 = Vec2::new(91.0, 67.0);
+
+impl Camera {
+    /// Part of the window drawn by the camera, in pixels
+    pub fn viewport(&self, screen: Rect) -> Rect {
+        match &self.viewport {
+            Some(viewport) => Rect::new(
+                screen.x + viewport.x * screen.w,
+                screen.y + viewport.y * screen.h,
+                viewport.w * screen.w,
+                viewport.h * screen.h
+            ),
+            None => screen
+        }
+    }
+
+    /// Transform from world coordinates to window coordinates, the camera position is at the center of its viewport
+    pub fn view(&self, screen: Rect) -> Mat4 {
+        let viewport = self.viewport(screen);
+        let position = self.position.to() + self.offset.to();
+
+        Mat4::from_translation(Vec3::new(viewport.x + viewport.w / 2.0, viewport.y + viewport.h / 2.0, 0.0))
+            * Mat4::from_rotation_z(-self.rotation)
+            * Mat4::from_scale(Vec3::new(self.zoom, self.zoom, 1.0))
+            * Mat4::from_translation(Vec3::new(-position.x, -position.y, 0.0))
+    }
+
+    pub fn world_to_screen(&self, screen: Rect, point: Vec2) -> Vec2 {
+        self.view(screen).transform_point3(point.extend(0.0)).truncate()
+    }
+
+    pub fn screen_to_world(&self, screen: Rect, point: Vec2) -> Vec2 {
+        self.view(screen).inverse().transform_point3(point.extend(0.0)).truncate()
+    }
+
+    /// Move the camera so its view stays inside its bounds, centered on an axis where the bounds are smaller than the view
+    fn clamp(&mut self) {
+        let bounds = match &self.bounds {
+            Some(bounds) => bounds.to(),
+            None => return
+        };
+
+        if self.zoom <= 0.0 {
+            return;
+        }
+
+        let viewport = self.viewport(self.screen.to());
+        let half = Vec2::new(viewport.w, viewport.h) / (2.0 * self.zoom);
+
+        self.position.x = if bounds.w < half.x * 2.0 {
+            bounds.x + bounds.w / 2.0
+        } else {
+            self.position.x.clamp(bounds.x + half.x, bounds.x + bounds.w - half.x)
+        };
+
+        self.position.y = if bounds.h < half.y * 2.0 {
+            bounds.y + bounds.h / 2.0
+        } else {
+            self.position.y.clamp(bounds.y + half.y, bounds.y + bounds.h - half.y)
+        };
+    }
+}
+
+/// System moving the `Camera` component toward the gameobject it follows, keeping it in its bounds and shaking it
+pub fn update_camera(go: &Gameobject, _: &AssetsLoader, delta: f32) -> mlua::Result<()> {
+    let mut camera = match go.get_component::<Camera>()? {
+        Some(camera) => camera,
+        None => return Ok(())
+    };
+
+    if let Some(target) = &camera.follow {
+        // The target can be missing for a frame, until it is updated once
+        if let Some(transform) = go.find_component::<Transform>(target)? {
+            let position = camera.position.to();
+            let target = transform.position.to();
+
+            let position = if camera.smoothing > 0.0 {
+                position + (target - position) * (1.0 - (-delta / camera.smoothing).exp())
+            } else {
+                target
+            };
+
+            camera.position = Vector { x: position.x, y: position.y };
+        }
+    }
+
+    camera.clamp();
+
+    if camera.shake_time > 0.0 {
+        camera.shake_time = (camera.shake_time - delta).max(0.0);
+
+        let strength = camera.shake_intensity * camera.shake_time / camera.shake_duration.max(f32::EPSILON);
+        let phase = SHAKE_FREQUENCY * camera.shake_time;
+
+        camera.offset = Vector { x: phase.x.sin() * strength, y: phase.y.cos() * strength };
+    } else {
+        camera.offset = Vector { x: 0.0, y: 0.0 };
+    }
+
+    // Only the fields moved by pebble are written, the script keeps its tables (layers, bounds, ...)
+    go.write_component_fields::<Camera>(|table| {
+        write_component(&camera.position, table, "position")?;
+        write_component(&camera.offset, table, "offset")?;
+        table.set("shake_time", camera.shake_time)
+    })?;
+
+    Ok(())
+}
+
+/// Conversion between the window and the world, available as `rust_camera` in lua
+struct LuaCamera;
+
+impl UserData for LuaCamera {
+    fn add_methods<'lua, M: mlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("screen_to_world", |_, _, (camera, point): (Camera, Vector)| {
+            let point = camera.screen_to_world(camera.screen.to(), point.to());
+            Ok(Vector { x: point.x, y: point.y })
+        });
+
+        methods.add_method("world_to_screen", |_, _, (camera, point): (Camera, Vector)| {
+            let point = camera.world_to_screen(camera.screen.to(), point.to());
+            Ok(Vector { x: point.x, y: point.y })
+        });
+    }
+}
+
+pub fn add_camera_library(script: &Script) {
+    script.get_state().globals().set("rust_camera", LuaCamera).unwrap();
+}
@@ -0,0 +1,189 @@
+use std::{cell::RefCell, error::Error, path::Path, rc::Rc};
+
+use chrono::Utc;
+use ggez::{graphics::{Canvas, Color, DrawParam, Image, ImageFormat}, input::keyboard::KeyCode, Context, GameResult};
+use mlua::UserData;
+
+pub type CaptureStorage = Rc<RefCell<Capture>>;
+
+/// Key saving the current frame as a screenshot
+pub const SCREENSHOT_KEY: KeyCode = KeyCode::F12;
+/// Key starting and stopping the recording
+pub const RECORDING_KEY: KeyCode = KeyCode::F10;
+
+/// Frames per second of a recording started with the key
+const DEFAULT_RECORDING_FPS: f32 = 60.0;
+
+/// Rows of pixels read back from the gpu must be a multiple of 256 bytes, so 64 pixels
+const ROW_ALIGNMENT: u32 = 64;
+
+/// Numbered png sequence written in a directory, one image per frame
+struct Recording {
+    directory: String,
+    frame: u32,
+    fps: f32
+}
+
+/// Copy of a frame waiting for the gpu to render it, saved in each file
+struct PendingFrame {
+    image: Image,
+    width: u32,
+    height: u32,
+    filenames: Vec<String>
+}
+
+/// Screenshots and recording of the window, requested by the keys or by the scripts with `Screen`
+pub struct Capture {
+    requests: Vec<String>,
+    recording: Option<Recording>,
+    pending: Option<PendingFrame>
+}
+
+impl Capture {
+    pub fn new() -> Self {
+        Self { requests: Vec::new(), recording: None, pending: None }
+    }
+
+    /// Save the next frame as a png
+    pub fn capture(&mut self, filename: &str) {
+        self.requests.push(filename.to_string());
+    }
+
+    pub fn screenshot(&mut self) {
+        self.capture(&format!("screenshot-{}.png", Utc::now().format("%Y%m%d-%H%M%S")));
+    }
+
+    /// Save every frame as `directory/00000.png`, `directory/00001.png`, ... The game is updated at `fps` while recording,
+    /// so the sequence plays at the right speed however long a frame takes to save.
+    pub fn start_recording(&mut self, directory: &str, fps: f32) -> Result<(), String> {
+        if fps <= 0.0 {
+            return Err(format!("The recording needs a positive fps, got {}", fps));
+        }
+
+        info!("Recording frames in {} at {} fps", directory, fps);
+        self.recording = Some(Recording { directory: directory.to_string(), frame: 0, fps });
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            info!("Recorded {} frames in {}", recording.frame, recording.directory);
+        }
+    }
+
+    pub fn toggle_recording(&mut self) {
+        if self.recording.is_some() {
+            self.stop_recording();
+        } else {
+            let directory = format!("recording-{}", Utc::now().format("%Y%m%d-%H%M%S"));
+            // The fps of the key is always positive
+            let _ = self.start_recording(&directory, DEFAULT_RECORDING_FPS);
+        }
+    }
+
+    /// Fixed time step of the recording, if any
+    pub fn recording_delta(&self) -> Option<f32> {
+        self.recording.as_ref().map(|recording| 1.0 / recording.fps)
+    }
+
+    /// Copy the frame of the window once its canvas is finished, when it is requested or recorded
+    pub fn copy_frame(&mut self, ctx: &mut Context) -> GameResult {
+        let mut filenames = std::mem::take(&mut self.requests);
+
+        if let Some(recording) = &mut self.recording {
+            filenames.push(format!("{}/{:05}.png", recording.directory, recording.frame));
+            recording.frame += 1;
+        }
+
+        if filenames.is_empty() {
+            return Ok(());
+        }
+
+        let frame = ctx.gfx.frame().clone();
+        let (width, height) = (frame.width(), frame.height());
+
+        // The copy is rgba whatever the format of the window, with a padding on the right so its rows can be read back
+        let image = Image::new_canvas_image(ctx, ImageFormat::Rgba8UnormSrgb, width.div_ceil(ROW_ALIGNMENT) * ROW_ALIGNMENT, height, 1);
+        let mut canvas = Canvas::from_image(ctx, image.clone(), Color::BLACK);
+        canvas.draw(&frame, DrawParam::default());
+        canvas.finish(ctx)?;
+
+        self.pending = Some(PendingFrame { image, width, height, filenames });
+        Ok(())
+    }
+
+    /// Write the frame copied during the last draw, the gpu rendered it since
+    pub fn save_pending(&mut self, ctx: &Context) {
+        let pending = match self.pending.take() {
+            Some(pending) => pending,
+            None => return
+        };
+
+        let pixels = match read_pixels(ctx, &pending) {
+            Ok(pixels) => pixels,
+            Err(err) => {
+                error!("Cannot read the frame to capture : {}", err);
+                return;
+            }
+        };
+
+        for filename in pending.filenames.iter() {
+            match save_png(filename, &pixels, pending.width, pending.height) {
+                Ok(_) => if self.recording.is_none() {
+                    info!("Frame saved to {}", filename);
+                },
+                Err(err) => error!("Cannot save the frame to {} : {}", filename, err)
+            }
+        }
+    }
+}
+
+/// Pixels of the frame without the padding of its rows
+fn read_pixels(ctx: &Context, pending: &PendingFrame) -> GameResult<Vec<u8>> {
+    let pixels = pending.image.to_pixels(ctx)?;
+    let (row, padded_row) = (pending.width as usize * 4, pending.image.width() as usize * 4);
+
+    Ok(pixels.chunks(padded_row).flat_map(|line| &line[..row]).copied().collect())
+}
+
+fn save_png(filename: &str, pixels: &[u8], width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+    if let Some(directory) = Path::new(filename).parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+
+    image::save_buffer_with_format(filename, pixels, width, height, image::ColorType::Rgba8, image::ImageFormat::Png)?;
+    Ok(())
+}
+
+/// Lua side handle of the captures, available as `rust_screen`
+pub struct LuaScreen {
+    capture: CaptureStorage
+}
+
+impl LuaScreen {
+    pub fn new(capture: CaptureStorage) -> Self {
+        Self { capture }
+    }
+}
+
+impl UserData for LuaScreen {
+    fn add_methods<'lua, M: mlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("capture", |_, this, filename: String| {
+            this.capture.borrow_mut().capture(&filename);
+            Ok(())
+        });
+
+        methods.add_method("start_recording", |_, this, (directory, fps): (String, Option<f32>)| {
+            this.capture.borrow_mut().start_recording(&directory, fps.unwrap_or(DEFAULT_RECORDING_FPS)).map_err(mlua::Error::RuntimeError)
+        });
+
+        methods.add_method("stop_recording", |_, this, ()| {
+            this.capture.borrow_mut().stop_recording();
+            Ok(())
+        });
+
+        methods.add_method("is_recording", |_, this, ()| {
+            Ok(this.capture.borrow().recording.is_some())
+        });
+    }
+}
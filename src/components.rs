@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+
+use ggez::glam::Vec2;
+use serde::Deserialize;
+
+use crate::{lua::LuaComponent, shader::UniformValue};
+
+#[derive(Clone, Debug, Deserialize, LuaComponent)]
+pub struct Vector {
+    pub x: f32,
+    pub y: f32
+}
+
+#[derive(Clone, Debug, LuaComponent)]
+pub struct Transform {
+    pub position: Vector,
+    pub rotation: f32,
+    pub scale: Vector
+}
+
+#[derive(Clone, Debug, Deserialize, LuaComponent)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32
+}
+
+#[derive(Clone, Debug, LuaComponent)]
+pub struct Material {
+    pub albedo: Color,
+    pub texture: Option<String>,
+    /// Wgsl fragment shader, loaded with `Assets:add_shader`
+    pub shader: Option<String>,
+    /// Values of the uniforms of the shader, by name
+    #[lua(default)]
+    pub uniforms: HashMap<String, UniformValue>,
+    /// How the material is blended with what is behind it : alpha, additive, multiply, replace or premultiplied
+    #[lua(default = "String::from(\"alpha\")", validate = "crate::renderer::validate_blend")]
+    pub blend: String,
+    /// Texture of the normals of the sprite, used by the lights. The sprite faces the camera when nil
+    pub normal_map: Option<String>
+}
+
+#[derive(Clone, Debug, LuaComponent)]
+pub struct Component<'a> {
+    pub c_type: String,
+    pub data: mlua::Table<'a>
+}
+
+/// Rectangle in pixels, used to select a part of a texture
+#[derive(Clone, Debug, LuaComponent)]
+pub struct Region {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32
+}
+
+/// Insets in pixels from each side of a rectangle
+#[derive(Clone, Debug, LuaComponent)]
+pub struct Borders {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32
+}
+
+/// Draw a part of the material texture, or a frame of a sprite sheet
+#[derive(Clone, Debug, LuaComponent)]
+pub struct Sprite {
+    /// Sprite sheet asset, its image replace the material texture
+    pub sheet: Option<String>,
+    /// Named frame of the sprite sheet
+    pub frame: Option<String>,
+    /// Part of the texture to draw, the whole texture when nil
+    pub region: Option<Region>,
+    /// Size in pixels on screen, the size of the region when nil
+    pub size: Option<Vector>,
+    /// Origin of the sprite, (0, 0) is the top left corner and (1, 1) the bottom right one
+    #[lua(default = "Vector { x: 0.5, y: 0.5 }")]
+    pub pivot: Vector,
+    #[lua(default)]
+    pub flip_x: bool,
+    #[lua(default)]
+    pub flip_y: bool,
+    /// Borders of a nine slice sprite, in pixels of the texture. Its corners keep their size when it is resized
+    pub slice: Option<Borders>
+}
+
+/// Play clips of an animation file on the sprite, driven by the engine
+#[derive(Clone, Debug, LuaComponent)]
+pub struct Animator {
+    /// Animation asset
+    pub animation: Option<String>,
+    /// Clip currently played
+    pub clip: Option<String>,
+    #[lua(default = "1.0")]
+    pub speed: f32,
+    #[lua(default = "true")]
+    pub playing: bool,
+    /// Index of the current frame in the clip
+    #[lua(default)]
+    pub frame: usize,
+    /// Time spent on the current frame
+    #[lua(default)]
+    pub time: f32,
+    /// 1 or -1, used by ping pong clips
+    #[lua(default = "1")]
+    pub direction: i32,
+    /// False until the first frame of the clip is sent, reset by `Animator:play`
+    #[lua(default)]
+    pub started: bool,
+    /// Cycles played since the clip started, for clips repeated a number of times
+    #[lua(default)]
+    pub cycle: u32
+}
+
+/// Draw a map made with tiled, its objects are given to the script once the map is loaded
+#[derive(Clone, Debug, LuaComponent)]
+pub struct Tilemap {
+    /// Tilemap asset
+    pub map: Option<String>,
+    /// Set by `Tilemap:load` when the objects of the map are given to the script
+    #[lua(default)]
+    pub loaded: bool
+}
+
+/// Render layer of the gameobject, layers are declared in `pebble.json`
+#[derive(Clone, Debug, LuaComponent)]
+pub struct Layer {
+    #[lua(default = "String::from(crate::project::DEFAULT_LAYER)")]
+    pub name: String,
+    /// Order inside the layer, the highest is drawn on top
+    #[lua(default)]
+    pub z_index: f32
+}
+
+/// Point of view on the world, every active camera draw the world in its viewport
+#[derive(Clone, Debug, LuaComponent)]
+pub struct Camera {
+    #[lua(default = "true")]
+    pub active: bool,
+    /// Cameras are drawn from the lowest order to the highest, a minimap is drawn after the main camera
+    #[lua(default)]
+    pub order: i32,
+    /// Layers drawn by the camera, every layer when nil
+    pub layers: Option<Vec<String>>,
+    /// Center of the view, in world coordinates
+    #[lua(default = "Vector { x: 0.0, y: 0.0 }")]
+    pub position: Vector,
+    #[lua(default = "1.0")]
+    pub zoom: f32,
+    #[lua(default)]
+    pub rotation: f32,
+    /// Part of the window drawn by the camera, from 0 to 1, the whole window when nil
+    pub viewport: Option<Region>,
+    /// Gameobject followed by the camera, the script name or `script#index` for spawned gameobjects
+    pub follow: Option<String>,
+    /// Time in seconds to catch up with the followed gameobject, 0 to stick to it
+    #[lua(default)]
+    pub smoothing: f32,
+    /// Area of the world the view cannot leave
+    pub bounds: Option<Region>,
+    #[lua(default)]
+    pub shake_intensity: f32,
+    #[lua(default)]
+    pub shake_duration: f32,
+    /// Time left before the shake stop
+    #[lua(default)]
+    pub shake_time: f32,
+    /// Offset of the shake, written by pebble
+    #[lua(default = "Vector { x: 0.0, y: 0.0 }")]
+    pub offset: Vector,
+    /// Render target drawn by the camera instead of the window
+    pub target: Option<String>,
+    /// Size in pixels of the window or of the render target, written by pebble before drawing
+    #[lua(default = "Region { x: 0.0, y: 0.0, w: 0.0, h: 0.0 }")]
+    pub screen: Region
+}
+
+/// Draw a string with a font loaded by `Assets:add_font`
+#[derive(Clone, Debug, LuaComponent)]
+pub struct Text {
+    #[lua(default)]
+    pub text: String,
+    /// Font asset, the default font of pebble when nil
+    pub font: Option<String>,
+    /// Size in pixels
+    #[lua(default = "16.0")]
+    pub size: f32,
+    #[lua(default = "Color { r: 255.0, g: 255.0, b: 255.0, a: 255.0 }")]
+    pub color: Color,
+    /// left, center or right
+    #[lua(default = "String::from(\"left\")", validate = "crate::text::validate_align")]
+    pub align: String,
+    /// Width in pixels after which lines are wrapped, never wrapped when nil
+    pub wrap_width: Option<f32>
+}
+
+/// Off-screen image drawn before the window, materials use it as a texture with its name
+#[derive(Clone, Debug, LuaComponent)]
+pub struct RenderTarget {
+    #[lua(default = "true")]
+    pub active: bool,
+    /// Name of the texture, the gameobject id (its script name or `script#index`) when nil
+    pub name: Option<String>,
+    /// Size in pixels
+    #[lua(validate = "crate::render_target::validate_size")]
+    pub width: u32,
+    #[lua(validate = "crate::render_target::validate_size")]
+    pub height: u32,
+    /// Targets are drawn from the lowest order to the highest, a target can show the ones drawn before it
+    #[lua(default)]
+    pub order: i32,
+    /// Layers drawn when no camera draws into the target, every layer when nil
+    pub layers: Option<Vec<String>>,
+    #[lua(default = "Color { r: 0.0, g: 0.0, b: 0.0, a: 0.0 }")]
+    pub clear_color: Color
+}
+
+/// Emit particles simulated and drawn by pebble, the settings can also come from a json file loaded with `Assets:add_particles`
+#[derive(Clone, Debug, Deserialize, LuaComponent)]
+#[serde(default)]
+#[lua(default)]
+pub struct ParticleEmitter {
+    /// Particles are emitted at `rate` while true
+    pub emitting: bool,
+    /// Particles asset, its settings replace the ones of the component
+    pub file: Option<String>,
+    /// Particles emitted per second
+    pub rate: f32,
+    /// Particles emitted at once on the next update, reset to 0 by pebble
+    pub burst: u32,
+    /// Lifetime in seconds, picked between x and y
+    pub lifetime: Vector,
+    /// Speed in pixels per second, picked between x and y
+    pub speed: Vector,
+    /// Direction of the particles in radians, 0 is to the right
+    pub direction: f32,
+    /// Angle around the direction in radians, particles go in every direction with 2 pi
+    pub spread: f32,
+    /// Acceleration in pixels per second squared
+    pub gravity: Vector,
+    /// Colors over the lifetime of a particle, evenly spaced and blended
+    pub colors: Vec<Color>,
+    /// Sizes in pixels over the lifetime of a particle, evenly spaced and blended
+    pub sizes: Vec<f32>,
+    /// Texture of the particles, squares when nil
+    pub texture: Option<String>,
+    /// Particles follow the gameobject when true, they stay where they were emitted otherwise
+    pub local_space: bool,
+    pub max_particles: usize
+}
+
+/// Light added to every lit layer, black when the scene has no ambient light
+#[derive(Clone, Debug, LuaComponent)]
+pub struct AmbientLight {
+    #[lua(default = "Color { r: 255.0, g: 255.0, b: 255.0, a: 255.0 }")]
+    pub color: Color,
+    #[lua(default = "1.0")]
+    pub intensity: f32
+}
+
+/// Light at the position of the gameobject, lighting the lit layers around it
+#[derive(Clone, Debug, LuaComponent)]
+pub struct PointLight {
+    #[lua(default = "Color { r: 255.0, g: 255.0, b: 255.0, a: 255.0 }")]
+    pub color: Color,
+    #[lua(default = "1.0")]
+    pub intensity: f32,
+    /// Distance in pixels where the light fades out
+    #[lua(default = "128.0")]
+    pub radius: f32,
+    /// How fast the light fades with the distance, 1 is linear
+    #[lua(default = "1.0")]
+    pub falloff: f32,
+    /// Height above the sprites in pixels, a low light makes the normal maps stand out
+    #[lua(default = "32.0")]
+    pub height: f32
+}
+
+/// State of the mouse, given to the scripts each frame as `Mouse`
+#[derive(Clone, Debug, LuaComponent)]
+pub struct Mouse {
+    /// Position on the window, in virtual pixels when the project has a resolution
+    pub position: Vector,
+    pub left: bool,
+    pub right: bool,
+    pub middle: bool
+}
+
+impl Vector {
+    pub fn to(&self) -> Vec2 {
+        Vec2 { x: self.x, y: self.y }
+    }
+}
+
+impl Color {
+    /// Colors are written from 0 to 255 in lua
+    pub fn to(&self) -> ggez::graphics::Color {
+        ggez::graphics::Color::new(self.r / 255.0, self.g / 255.0, self.b / 255.0, self.a / 255.0)
+    }
+}
+
+impl Region {
+    pub fn to(&self) -> ggez::graphics::Rect {
+        ggez::graphics::Rect::new(self.x, self.y, self.w, self.h)
+    }
+}
@@ -2,14 +2,25 @@ use std::{path::Path, rc::Rc, cell::RefCell};
 
 use ggez::{ContextBuilder, event::{self}};
 
-use crate::{gameobject::Gameobject, lua::Script, state::State};
+use crate::{assets::AssetsLoader, gameobject::Gameobject, lua::{Script, ScriptWatcher}, state::State, events::{EventBus, EventStorage, Spawn}, debug::{DebugDraw, DebugStorage}, capture::{Capture, CaptureStorage}, project::{ProjectConfig, PROJECT_CONFIG_FILE}};
 
 pub type Middleware = fn(&Script);
+/// Rust side logic run on every loaded gameobject after its lua update (physics, animation, ...)
+pub type System = fn(&Gameobject, &AssetsLoader, f32) -> mlua::Result<()>;
 pub type MiddlewareStorage = Rc<RefCell<Vec<Middleware>>>;
 pub type GameobjectStorage = Vec<Gameobject>;
 
 pub struct Core {
     pub gameobjects: GameobjectStorage,
+    pub events: EventStorage,
+    /// Debug shapes shared by every gameobject
+    pub debug: DebugStorage,
+    /// Screenshots and recording requested by the scripts
+    pub capture: CaptureStorage,
+    pub systems: Vec<System>,
+    pub config: ProjectConfig,
+    /// Scripts of the gameobjects, reloaded when they change on disk
+    pub watcher: ScriptWatcher,
     middlewares: MiddlewareStorage
 }
 
@@ -17,6 +28,12 @@ impl Core {
     pub fn new() -> Self {
         Self {
             gameobjects: Vec::new(),
+            events: Rc::new(RefCell::new(EventBus::new())),
+            debug: Rc::new(RefCell::new(DebugDraw::new())),
+            capture: Rc::new(RefCell::new(Capture::new())),
+            systems: Vec::new(),
+            config: ProjectConfig::default(),
+            watcher: ScriptWatcher::new(),
             middlewares: Rc::new(RefCell::new(Vec::new()))
         }
     }
@@ -27,18 +44,47 @@ impl Core {
         self
     }
 
+    pub fn add_system(&mut self, system: System) -> &mut Self {
+        self.systems.push(system);
+
+        self
+    }
+
     pub fn add_gameobject(&mut self, name: &str) -> &mut Self {
-        self.gameobjects.push(Gameobject::new(name,Rc::clone(&self.middlewares)).unwrap());
+        self.gameobjects.push(Gameobject::new(name,Rc::clone(&self.middlewares), Rc::clone(&self.events), Rc::clone(&self.debug), Rc::clone(&self.capture)).unwrap());
+        self.watcher.watch(name);
 
         self
     }
 
-    pub fn run(self) -> Result<Self, Box<dyn std::error::Error>> {
+    /// Add a gameobject while the game is running, with the id given by the event bus to be found by other scripts
+    pub fn spawn_gameobject(&mut self, spawn: Spawn) -> Result<&mut Gameobject, Box<dyn std::error::Error>> {
+        let go = Gameobject::new(&spawn.script, Rc::clone(&self.middlewares), Rc::clone(&self.events), Rc::clone(&self.debug), Rc::clone(&self.capture))?.with_instance(&spawn.id, spawn.properties);
+
+        self.watcher.watch(&spawn.script);
+        self.gameobjects.push(go);
+
+        Ok(self.gameobjects.last_mut().unwrap())
+    }
+
+    /// Remove a gameobject while the game is running, return false if there is none with this id
+    pub fn despawn_gameobject(&mut self, id: &str) -> bool {
+        let count = self.gameobjects.len();
+
+        self.gameobjects.retain(|go| go.get_id() != id);
+        self.events.borrow_mut().unpublish(id);
+
+        self.gameobjects.len() != count
+    }
+
+    pub fn run(mut self) -> Result<Self, Box<dyn std::error::Error>> {
         let (ctx, event_loop) = ContextBuilder::new("my_game", "Corentin deblock")
             .build()?;
 
         ctx.fs.mount(Path::new("./assets/"), true);
 
+        self.config = ProjectConfig::load(PROJECT_CONFIG_FILE);
+
         let mut game_loop = State::new(self, &ctx);
 
         game_loop.init();
@@ -0,0 +1,161 @@
+use std::{cell::RefCell, rc::Rc};
+
+use ggez::{glam::{Mat4, Vec2}, graphics::{Canvas, Color, DrawMode, DrawParam, MeshBuilder, Rect, Text}, Context};
+use mlua::UserData;
+
+use crate::{components, PebbleMode, PEBBLE_CONFIG};
+
+pub type DebugStorage = Rc<RefCell<DebugDraw>>;
+
+/// Key showing and hiding the gameobjects on the debug overlay
+pub const OVERLAY_KEY: ggez::input::keyboard::KeyCode = ggez::input::keyboard::KeyCode::F3;
+
+/// Size of the texts of the overlay, in pixels
+const TEXT_SIZE: f32 = 12.0;
+/// Length of the axes drawn at the origin of the gameobjects, in pixels
+const AXIS_LENGTH: f32 = 16.0;
+
+/// Shape drawn on top of the scene for a single frame, in world coordinates
+pub enum DebugCommand {
+    Line { from: Vec2, to: Vec2, color: Color },
+    Rect { rect: Rect, color: Color },
+    Text { text: String, position: Vec2, color: Color }
+}
+
+/// Debug shapes of the frame, requested by the scripts with `Debug` or by the overlay.
+/// Nothing is drawn in release mode.
+pub struct DebugDraw {
+    commands: Vec<DebugCommand>,
+    enabled: bool,
+    /// Draw the origin, axes, quad and name of every gameobject
+    overlay: bool
+}
+
+impl DebugDraw {
+    pub fn new() -> Self {
+        Self { commands: Vec::new(), enabled: matches!(PEBBLE_CONFIG.mode, PebbleMode::Debug), overlay: false }
+    }
+
+    pub fn is_overlay_visible(&self) -> bool {
+        self.enabled && self.overlay
+    }
+
+    pub fn set_overlay(&mut self, overlay: bool) {
+        self.overlay = overlay;
+    }
+
+    pub fn toggle_overlay(&mut self) {
+        self.overlay = !self.overlay;
+    }
+
+    pub fn push(&mut self, command: DebugCommand) {
+        if self.enabled {
+            self.commands.push(command);
+        }
+    }
+
+    /// Origin and axes of a transform, the x axis in red and the y axis in green
+    pub fn push_axes(&mut self, position: Vec2, rotation: f32) {
+        let x = Vec2::from_angle(rotation) * AXIS_LENGTH;
+
+        self.push(DebugCommand::Line { from: position, to: position + x, color: Color::RED });
+        self.push(DebugCommand::Line { from: position, to: position + x.perp(), color: Color::GREEN });
+    }
+
+    /// Outline going through every corner
+    pub fn push_polygon(&mut self, corners: &[Vec2], color: Color) {
+        for (index, from) in corners.iter().enumerate() {
+            self.push(DebugCommand::Line { from: *from, to: corners[(index + 1) % corners.len()], color });
+        }
+    }
+
+    /// Drop the shapes of the frame, once every camera drew them
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    /// Draw the shapes on top of what the canvas already has, `view` is the transform from the world to the pixels of the canvas.
+    /// Only the positions are transformed, so lines stay one pixel wide and texts stay sharp however the scene is scaled.
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas, view: Mat4) {
+        let to_canvas = |point: Vec2| view.transform_point3(point.extend(0.0)).truncate();
+
+        let mut builder = MeshBuilder::new();
+        let mut has_shapes = false;
+
+        for command in self.commands.iter() {
+            let result = match command {
+                DebugCommand::Line { from, to, color } => {
+                    let (from, to) = (to_canvas(*from), to_canvas(*to));
+
+                    // A line without length cannot be built
+                    match from != to {
+                        true => builder.line(&[from, to], 1.0, *color).map(|_| true),
+                        false => Ok(false)
+                    }
+                },
+                DebugCommand::Rect { rect, color } => {
+                    let corners = [rect.point().into(), Vec2::new(rect.right(), rect.y), Vec2::new(rect.right(), rect.bottom()), Vec2::new(rect.x, rect.bottom())];
+                    builder.polygon(DrawMode::stroke(1.0), &corners.map(to_canvas), *color).map(|_| true)
+                },
+                DebugCommand::Text { .. } => Ok(false)
+            };
+
+            match result {
+                Ok(shape) => has_shapes |= shape,
+                Err(err) => error!("Cannot draw a debug shape : {}", err)
+            }
+        }
+
+        if has_shapes {
+            canvas.draw(&ggez::graphics::Mesh::from_data(ctx, builder.build()), DrawParam::default());
+        }
+
+        for command in self.commands.iter() {
+            if let DebugCommand::Text { text, position, color } = command {
+                let mut text = Text::new(text.as_str());
+                text.set_scale(TEXT_SIZE);
+
+                canvas.draw(&text, DrawParam::default().dest(to_canvas(*position)).color(*color));
+            }
+        }
+    }
+}
+
+fn to_color(color: Option<components::Color>) -> Color {
+    color.map(|color| color.to()).unwrap_or(Color::WHITE)
+}
+
+/// Lua side handle of the debug drawing, available as `rust_debug`
+pub struct LuaDebug {
+    debug: DebugStorage
+}
+
+impl LuaDebug {
+    pub fn new(debug: DebugStorage) -> Self {
+        Self { debug }
+    }
+}
+
+impl UserData for LuaDebug {
+    fn add_methods<'lua, M: mlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("draw_line", |_, this, (x1, y1, x2, y2, color): (f32, f32, f32, f32, Option<components::Color>)| {
+            this.debug.borrow_mut().push(DebugCommand::Line { from: Vec2::new(x1, y1), to: Vec2::new(x2, y2), color: to_color(color) });
+            Ok(())
+        });
+
+        methods.add_method("draw_rect", |_, this, (x, y, w, h, color): (f32, f32, f32, f32, Option<components::Color>)| {
+            this.debug.borrow_mut().push(DebugCommand::Rect { rect: Rect::new(x, y, w, h), color: to_color(color) });
+            Ok(())
+        });
+
+        methods.add_method("draw_text", |_, this, (text, x, y, color): (String, f32, f32, Option<components::Color>)| {
+            this.debug.borrow_mut().push(DebugCommand::Text { text, position: Vec2::new(x, y), color: to_color(color) });
+            Ok(())
+        });
+
+        methods.add_method("set_overlay", |_, this, overlay: bool| {
+            this.debug.borrow_mut().set_overlay(overlay);
+            Ok(())
+        });
+    }
+}
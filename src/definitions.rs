@@ -0,0 +1,95 @@
+use std::error::Error;
+
+use crate::{lua::LuaDefinition, assets::LuaAsset, components::{Vector, Transform, Color, Material, Component, Region, Borders, Sprite, Animator, Tilemap, Layer, Camera, Text, RenderTarget, ParticleEmitter, AmbientLight, PointLight, Mouse}};
+
+pub const DEFAULT_DEFINITIONS_FILE: &str = "pebble.d.lua";
+
+/// Split generic arguments at their first top level comma, `String, Vec<(f32, f32)>` become `String` and `Vec<(f32, f32)>`
+fn split_arguments(arguments: &str) -> Option<(&str, &str)> {
+    let mut depth = 0;
+
+    for (index, c) in arguments.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            ',' if depth == 0 => return Some((&arguments[..index], &arguments[index + 1..])),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Convert a rust type (as written in the struct) to a lua language server type
+fn to_lua_type(rust_type: &str) -> String {
+    let rust_type: String = rust_type.chars().filter(|c| !c.is_whitespace()).collect();
+
+    if let Some(inner) = rust_type.strip_prefix("Option<").and_then(|t| t.strip_suffix('>')) {
+        return format!("{}|nil", to_lua_type(inner));
+    }
+
+    if let Some(inner) = rust_type.strip_prefix("Vec<").and_then(|t| t.strip_suffix('>')) {
+        return format!("{}[]", to_lua_type(inner));
+    }
+
+    if let Some((key, value)) = rust_type.strip_prefix("HashMap<").and_then(|t| t.strip_suffix('>')).and_then(split_arguments) {
+        return format!("table<{}, {}>", to_lua_type(key), to_lua_type(value));
+    }
+
+    // Remove the path and the lifetime, mlua::Table<'a> become Table
+    let name = rust_type.split('<').next().unwrap_or(&rust_type);
+    let name = name.rsplit("::").next().unwrap_or(name);
+
+    match name {
+        "f32" | "f64" | "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" | "usize" | "isize" => "number".to_string(),
+        "bool" => "boolean".to_string(),
+        "String" | "str" | "&str" => "string".to_string(),
+        "Table" => "table".to_string(),
+        "Function" => "function".to_string(),
+        "Value" => "any".to_string(),
+        "UniformValue" => "number|number[]".to_string(),
+        _ => name.to_string()
+    }
+}
+
+fn class<T: LuaDefinition>() -> String {
+    let mut output = format!("---@class {}\n", T::lua_name());
+
+    for field in T::lua_fields() {
+        output.push_str(&format!("---@field {} {}\n", field.name, to_lua_type(field.rust_type)));
+    }
+
+    output
+}
+
+/// Generate the lua language server definitions of every struct shared between lua and rust
+pub fn generate() -> String {
+    let classes = [
+        class::<Vector>(),
+        class::<Transform>(),
+        class::<Color>(),
+        class::<Material>(),
+        class::<Component>(),
+        class::<Region>(),
+        class::<Borders>(),
+        class::<Sprite>(),
+        class::<Animator>(),
+        class::<Tilemap>(),
+        class::<Layer>(),
+        class::<Camera>(),
+        class::<Text>(),
+        class::<RenderTarget>(),
+        class::<ParticleEmitter>(),
+        class::<AmbientLight>(),
+        class::<PointLight>(),
+        class::<Mouse>(),
+        class::<LuaAsset>(),
+    ];
+
+    format!("---@meta\n-- Generated by pebble from the rust types, do not edit.\n-- Run `cargo run -- types` to regenerate it.\n\n{}", classes.join("\n"))
+}
+
+pub fn write(filename: &str) -> Result<(), Box<dyn Error>> {
+    std::fs::write(filename, generate())?;
+    Ok(())
+}
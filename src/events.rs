@@ -0,0 +1,223 @@
+use std::{collections::{HashMap, HashSet}, rc::Rc, cell::RefCell, ffi::c_void};
+
+use mlua::{FromLua, ToLua, UserData};
+
+/// Max depth of nested tables that can be sent between lua states
+const MAX_PAYLOAD_DEPTH: usize = 32;
+
+pub type EventStorage = Rc<RefCell<EventBus>>;
+
+/// Lua value copied out of a lua state so it can be sent to another one
+#[derive(Clone, Debug)]
+pub enum Payload {
+    Nil,
+    Boolean(bool),
+    Integer(i64),
+    Number(f64),
+    String(String),
+    Table(Vec<(Payload, Payload)>)
+}
+
+impl Payload {
+    /// Copy a value for reading only, functions and userdata inside tables are skipped instead of failing
+    pub fn snapshot(value: mlua::Value) -> mlua::Result<Self> {
+        Self::from_value(value, &mut HashSet::new(), 0, false)
+    }
+
+    /// Value of a string key, for tables only
+    pub fn get(&self, key: &str) -> Option<&Payload> {
+        match self {
+            Payload::Table(entries) => entries.iter()
+                .find(|(entry, _)| matches!(entry, Payload::String(entry) if entry == key))
+                .map(|(_, value)| value),
+            _ => None
+        }
+    }
+
+    /// `parents` are the tables being copied around the value, a table inside itself is a cycle and is never walked again
+    fn from_value(value: mlua::Value, parents: &mut HashSet<*const c_void>, depth: usize, strict: bool) -> mlua::Result<Self> {
+        if depth > MAX_PAYLOAD_DEPTH {
+            return Err(mlua::Error::RuntimeError(String::from("Payload is too deep")));
+        }
+
+        Ok(match value {
+            mlua::Value::Nil => Payload::Nil,
+            mlua::Value::Boolean(value) => Payload::Boolean(value),
+            mlua::Value::Integer(value) => Payload::Integer(value),
+            mlua::Value::Number(value) => Payload::Number(value),
+            mlua::Value::String(value) => Payload::String(value.to_str()?.to_string()),
+            mlua::Value::Table(table) => {
+                let pointer = table.to_pointer();
+
+                if !parents.insert(pointer) {
+                    return Err(mlua::Error::RuntimeError(String::from("Payload contains a cyclic table")));
+                }
+
+                let mut entries = Vec::new();
+
+                for pair in table.pairs::<mlua::Value, mlua::Value>() {
+                    let (key, value) = pair?;
+
+                    match (Self::from_value(key, parents, depth + 1, strict), Self::from_value(value, parents, depth + 1, strict)) {
+                        (Ok(key), Ok(value)) => entries.push((key, value)),
+                        (Err(err), _) | (_, Err(err)) => if strict {
+                            parents.remove(&pointer);
+                            return Err(err);
+                        }
+                    }
+                }
+
+                parents.remove(&pointer);
+                Payload::Table(entries)
+            },
+            mlua::Value::Error(err) => return Err(err),
+            other => return Err(mlua::Error::RuntimeError(format!("{} cannot be sent to another script", other.type_name())))
+        })
+    }
+}
+
+impl<'lua> FromLua<'lua> for Payload {
+    fn from_lua(lua_value: mlua::Value<'lua>, _: &'lua mlua::Lua) -> mlua::Result<Self> {
+        Self::from_value(lua_value, &mut HashSet::new(), 0, true)
+    }
+}
+
+impl<'lua> ToLua<'lua> for Payload {
+    fn to_lua(self, lua: &'lua mlua::Lua) -> mlua::Result<mlua::Value<'lua>> {
+        Ok(match self {
+            Payload::Nil => mlua::Value::Nil,
+            Payload::Boolean(value) => mlua::Value::Boolean(value),
+            Payload::Integer(value) => mlua::Value::Integer(value),
+            Payload::Number(value) => mlua::Value::Number(value),
+            Payload::String(value) => mlua::Value::String(lua.create_string(&value)?),
+            Payload::Table(entries) => {
+                let table = lua.create_table()?;
+
+                for (key, value) in entries {
+                    table.raw_set(key, value)?;
+                }
+
+                mlua::Value::Table(table)
+            }
+        })
+    }
+}
+
+/// Message emitted by a script, dispatched to every gameobject on the next update
+#[derive(Clone, Debug)]
+pub struct Event {
+    pub name: String,
+    pub sender: String,
+    pub payload: Payload
+}
+
+/// Gameobject created by a script, added on the next update
+#[derive(Clone, Debug)]
+pub struct Spawn {
+    pub id: String,
+    pub script: String,
+    pub properties: Payload
+}
+
+/// Engine wide message bus, also keep a copy of every gameobject components so scripts can read each other
+pub struct EventBus {
+    queue: Vec<Event>,
+    spawns: Vec<Spawn>,
+    /// Ids of the gameobjects removed on the next update
+    despawns: Vec<String>,
+    /// Gameobjects spawned since the start, gives a unique id to each of them
+    spawned: usize,
+    gameobjects: HashMap<String, Payload>
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self { queue: Vec::new(), spawns: Vec::new(), despawns: Vec::new(), spawned: 0, gameobjects: HashMap::new() }
+    }
+
+    pub fn emit(&mut self, event: Event) {
+        self.queue.push(event);
+    }
+
+    pub fn drain(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.queue)
+    }
+
+    /// Id of the gameobject once spawned, its script and a counter
+    pub fn spawn(&mut self, script: String, properties: Payload) -> String {
+        self.spawned += 1;
+
+        let id = format!("{}#{}", script, self.spawned);
+        self.spawns.push(Spawn { id: id.clone(), script, properties });
+        id
+    }
+
+    pub fn drain_spawns(&mut self) -> Vec<Spawn> {
+        std::mem::take(&mut self.spawns)
+    }
+
+    pub fn despawn(&mut self, id: &str) {
+        self.despawns.push(id.to_string());
+    }
+
+    pub fn drain_despawns(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.despawns)
+    }
+
+    pub fn publish(&mut self, name: &str, components: Payload) {
+        self.gameobjects.insert(name.to_string(), components);
+    }
+
+    pub fn unpublish(&mut self, name: &str) {
+        self.gameobjects.remove(name);
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Payload> {
+        self.gameobjects.get(name)
+    }
+
+    /// Data of the first component with the given type in the copy of a gameobject components
+    pub fn find_component(&self, name: &str, c_type: &str) -> Option<&Payload> {
+        match self.find(name)?.get("data")? {
+            Payload::Table(components) => components.iter()
+                .map(|(_, component)| component)
+                .find(|component| matches!(component.get("c_type"), Some(Payload::String(value)) if value == c_type))
+                .and_then(|component| component.get("data")),
+            _ => None
+        }
+    }
+}
+
+/// Lua side handle of the event bus, bound to the gameobject that own the script
+pub struct LuaEvents {
+    name: String,
+    bus: EventStorage
+}
+
+impl LuaEvents {
+    pub fn new(name: &str, bus: EventStorage) -> Self {
+        Self { name: name.to_string(), bus }
+    }
+}
+
+impl UserData for LuaEvents {
+    fn add_methods<'lua, M: mlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("emit", |_, this, (name, payload): (String, Payload)| {
+            this.bus.borrow_mut().emit(Event { name, sender: this.name.clone(), payload });
+            Ok(())
+        });
+
+        methods.add_method("spawn", |_, this, (script, properties): (String, Payload)| {
+            Ok(this.bus.borrow_mut().spawn(script, properties))
+        });
+
+        methods.add_method("despawn", |_, this, id: Option<String>| {
+            this.bus.borrow_mut().despawn(&id.unwrap_or_else(|| this.name.clone()));
+            Ok(())
+        });
+
+        methods.add_method("find", |_, this, name: String| {
+            Ok(this.bus.borrow().find(&name).cloned().unwrap_or(Payload::Nil))
+        });
+    }
+}
@@ -1,77 +1,206 @@
-use std::{error::Error, sync::mpsc::{channel, Receiver}, path::Path};
+use std::{collections::{HashMap, HashSet}, error::Error, rc::Rc, cell::{Cell, RefCell}};
 
-use ggez::graphics::{self, DrawParam, Rect};
-use mlua::Function;
-use notify::{Watcher, Event, EventKind};
-use crate::{lua::{Script, LuaArray, LuaComponent}, Component, Material, Transform, core::MiddlewareStorage, assets::AssetsLoader};
+use ggez::{graphics::{BlendMode, Color, DrawParam, Mesh, MeshData, Rect}, glam::{Mat4, Vec2, Vec3}};
+use mlua::{Function, Table, ToLua, ToLuaMulti};
+use crate::{lua::{Script, LuaArray, LuaComponent, LuaDefinition, write_component}, components::{Component, Material, Transform, Sprite, Tilemap, Vector, Layer, Camera, Region, Text, RenderTarget, Mouse, ParticleEmitter, AmbientLight, PointLight}, core::MiddlewareStorage, assets::{AssetsLoader, LuaAsset}, events::{EventStorage, LuaEvents, Event, Payload}, renderer::{Renderer, RenderOrder, ShaderDraw, SpriteDraw, MeshDraw, blend_mode, sprite_transform}, bitmap_font::glyph_transform, graphics::{GraphicsCommand, GraphicsStorage, LuaGraphics, Graphics}, particles::{Particles, emitter_settings}, nine_slice::{nine_slice, slice_origin}, lighting::LightDraw, debug::{DebugCommand, DebugStorage, LuaDebug}, capture::{CaptureStorage, LuaScreen}};
 
 /// Gameobject are object that interact with the world, gameobject are like a character, ennemy, map, etc...
 pub struct Gameobject {
     script: Script,
     name: String,
-    receiver: Receiver<Result<Event, notify::Error>>,
-    watcher: notify::INotifyWatcher,
+    /// Name used by the event bus, the script name unless the gameobject was spawned
+    id: String,
+    /// Given by the script that spawned the gameobject, available as `Properties` in lua
+    properties: Payload,
     reload_asset: bool,
     middlewares: MiddlewareStorage,
+    events: EventStorage,
+    /// Shapes, images and texts requested by the `Draw` function of the script
+    graphics: GraphicsStorage,
+    /// Particles of the `ParticleEmitter` component
+    particles: RefCell<Particles>,
+    debug: DebugStorage,
+    capture: CaptureStorage,
+    /// Transform of the quad drawn during the last frame, shown on the debug overlay
+    bounds: Cell<Option<Mat4>>,
     pub is_loaded: bool
 }
 
 impl Gameobject {
-    pub fn new(name: &str, middlewares: MiddlewareStorage) -> Result<Self, Box<dyn Error>> {
+    pub fn new(name: &str, middlewares: MiddlewareStorage, events: EventStorage, debug: DebugStorage, capture: CaptureStorage) -> Result<Self, Box<dyn Error>> {
         let script = Script::from_file(name)?;
 
-        let (sender, receiver) = channel();
-        
-        let mut watcher = notify::recommended_watcher(sender)?;
-
-        watcher.watch(Path::new(name), notify::RecursiveMode::Recursive)?;
-
         Ok(Self {
             name: name.to_string(),
+            id: name.to_string(),
+            properties: Payload::Nil,
             script,
-            receiver,
-            watcher,
             middlewares,
+            events,
+            graphics: Rc::new(RefCell::new(Graphics::new())),
+            particles: RefCell::new(Particles::new()),
+            debug,
+            capture,
+            bounds: Cell::new(None),
             is_loaded: false,
             reload_asset: true
         })
     }
 
+    pub fn with_instance(mut self, id: &str, properties: Payload) -> Self {
+        self.id = id.to_string();
+        self.properties = properties;
+        self
+    }
+
     pub fn init(&mut self) {
         for middleware in self.middlewares.borrow().iter() {
             middleware(&self.script)
         }
 
+        self.script.get_state().globals().set("rust_events", LuaEvents::new(&self.id, Rc::clone(&self.events))).unwrap();
+        self.script.get_state().globals().set("rust_graphics", LuaGraphics::new(Rc::clone(&self.graphics))).unwrap();
+        self.script.get_state().globals().set("rust_debug", LuaDebug::new(Rc::clone(&self.debug))).unwrap();
+        self.script.get_state().globals().set("rust_screen", LuaScreen::new(Rc::clone(&self.capture))).unwrap();
+        self.script.get_state().globals().set("Properties", self.properties.clone()).unwrap();
+
         match self.script.run() {
             Ok(_) => self.loaded(),
             Err(err) => self.failed(err),
         }
     }
 
+    /// Name used by the event bus, `script#index` for spawned gameobjects
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
     pub fn get_script(&self) -> &Script {
         &self.script
     }
 
+    /// Find the data table of the first component with the given type
+    fn get_component_table(&self, c_type: &str) -> mlua::Result<Option<Table<'_>>> {
+        let components : LuaArray<Component> = self.script.get_state().globals().get("Components")?;
+
+        for result in components.iter() {
+            let (_, component) = result?;
+
+            if component.c_type == c_type {
+                return Ok(Some(component.data));
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn get_component<'lua, T: LuaComponent<'lua> + LuaDefinition>(&'lua self) -> mlua::Result<Option<T>> {
+        match self.get_component_table(T::lua_name())? {
+            Some(table) => Ok(Some(T::from_lua_table(table)?)),
+            None => Ok(None)
+        }
+    }
+
+    /// Write a component modified from rust back into the script, return false if the script does not have it
+    pub fn set_component<'lua, T: LuaComponent<'lua> + LuaDefinition>(&'lua self, component: &T) -> mlua::Result<bool> {
+        match self.get_component_table(T::lua_name())? {
+            Some(table) => {
+                component.write_to_table(&table)?;
+                Ok(true)
+            },
+            None => Ok(false)
+        }
+    }
+
+    /// Write a single field of a component, its other fields keep the values and tables set by the script
+    pub fn set_component_field<'lua, T: LuaDefinition, V: ToLua<'lua>>(&'lua self, name: &str, value: V) -> mlua::Result<bool> {
+        match self.get_component_table(T::lua_name())? {
+            Some(table) => {
+                table.set(name, value)?;
+                Ok(true)
+            },
+            None => Ok(false)
+        }
+    }
+
+    /// Write some fields of a component with `write`, given its table, the fields it does not write are left as the script set them
+    pub fn write_component_fields<'lua, T: LuaDefinition>(&'lua self, write: impl FnOnce(&Table<'lua>) -> mlua::Result<()>) -> mlua::Result<bool> {
+        match self.get_component_table(T::lua_name())? {
+            Some(table) => {
+                write(&table)?;
+                Ok(true)
+            },
+            None => Ok(false)
+        }
+    }
+
+    /// Read a component of another gameobject, as it was after its last update
+    pub fn find_component<'lua, T: LuaComponent<'lua> + LuaDefinition>(&'lua self, id: &str) -> mlua::Result<Option<T>> {
+        let data = self.events.borrow().find_component(id, T::lua_name()).cloned();
+
+        match data.map(|data| data.to_lua(self.script.get_state())).transpose()? {
+            Some(mlua::Value::Table(table)) => Ok(Some(T::from_lua_table(table)?)),
+            _ => Ok(None)
+        }
+    }
+
+    /// Call a method of a component table, like `component:method(args)` in lua
+    pub fn call_component<'lua, A: ToLuaMulti<'lua>>(&'lua self, c_type: &str, method: &str, args: A) -> mlua::Result<()> {
+        match self.get_component_table(c_type)? {
+            Some(table) => {
+                let function : Function = table.get(method)?;
+                function.bind(table)?.call::<_, ()>(args)
+            },
+            None => Ok(())
+        }
+    }
+
     pub fn update(&mut self, delta: f32) {
         if self.is_loaded {
-            match self.script.get::<_, Function>("Update").unwrap().call::<f32, ()>(delta) {
-                Ok(_) => {},
-                Err(_) => {},
+            let result = match self.script.get_state().globals().get::<_, Option<Function>>("Update") {
+                Ok(Some(update)) => update.call::<f32, ()>(delta),
+                Ok(None) => Ok(()),
+                Err(err) => Err(err)
+            };
+
+            if let Err(err) = result {
+                self.failed(err);
+                return;
+            }
+
+            match self.script.get_state().globals().get::<_, mlua::Value>("Components").and_then(Payload::snapshot) {
+                Ok(components) => self.events.borrow_mut().publish(&self.id, components),
+                Err(err) => error!("Cannot publish components of {} : {}", self.id, err),
             }
         }
     }
 
-    pub fn watch(&mut self) {
-        match self.receiver.try_recv() {
-            Ok(event) => { 
-                let event_type = event.unwrap().kind;
-                if event_type == EventKind::Access(notify::event::AccessKind::Close(notify::event::AccessMode::Write)) {
-                    self.script = Script::from_file(&self.name).unwrap();
-                    self.init();
-                }
+    pub fn dispatch(&self, event: &Event) {
+        if self.is_loaded {
+            let result = self.script.get::<_, Table>("Events").and_then(|events| {
+                let dispatch : Function = events.get("dispatch")?;
+                Ok(dispatch.call::<_, ()>((events, event.name.clone(), event.payload.clone(), event.sender.clone()))?)
+            });
+
+            if let Err(err) = result {
+                error!("Error when dispatching event {} to {} : {}", event.name, self.id, err);
+            }
+        }
+    }
+
+    /// Reload the script when it is one of the scripts written since the last update
+    pub fn watch(&mut self, changed: &HashSet<String>) {
+        if !changed.contains(&self.name) {
+            return;
+        }
+
+        match Script::from_file(&self.name) {
+            Ok(script) => {
+                self.script = script;
+                self.init();
             },
-            Err(_) => {},
-        };
+            Err(err) => error!("Cannot reload script {} : {}", self.name, err)
+        }
     }
 
     fn loaded(&mut self) {
@@ -80,9 +209,10 @@ impl Gameobject {
         debug!("Reloaded script {}", self.script.get_name());
     }
 
-    fn failed(&mut self, err: mlua::Error) {
+    /// Stop running the script until it is reloaded
+    pub fn failed(&mut self, err: mlua::Error) {
         self.is_loaded = false;
-        error!("{}", err)
+        error!("{} : {}", self.id, err)
     }
 
     pub fn is_assets_reloaded(&mut self) -> bool {
@@ -91,71 +221,416 @@ impl Gameobject {
         store.clone()
     }
 
-    pub fn render(&self, ctx: &mut ggez::Context, canvas: &mut ggez::graphics::Canvas, asset_loader: &AssetsLoader) {
-        let components : LuaArray<Component> = self.script.get("Components").unwrap();
-        let mesh = rect(ctx, Rect::new(-16.0, -16.0, 32.0, 32.0));
+    /// Assets of a kind (textures, sprite_sheets, ...) requested by the script with `Assets:add_*`
+    pub fn get_assets(&self, kind: &str) -> mlua::Result<Vec<LuaAsset>> {
+        let assets : Table = self.script.get_state().globals().get("Assets")?;
+        let files : Option<Table> = assets.get(kind)?;
 
-        let mut draw_param = DrawParam::default();
-        let mut texture : Option<&ggez::graphics::Image> = None;
+        match files {
+            Some(files) => files.pairs::<String, LuaAsset>().map(|pair| pair.map(|(_, asset)| asset)).collect(),
+            None => Ok(Vec::new())
+        }
+    }
 
-        for result in components.iter() {
-            let (_, component) : (String, Component) = result.unwrap();
-            if component.c_type == "Material" {
-                let material = Material::from_lua_table(component.data).unwrap();
-
-                draw_param = draw_param.color(ggez::graphics::Color{ 
-                    r: material.albedo.r / 255.0, 
-                    g: material.albedo.g / 255.0, 
-                    b: material.albedo.b / 255.0, 
-                    a: material.albedo.a / 255.0
-                });
+    pub fn get_particles(&self) -> &RefCell<Particles> {
+        &self.particles
+    }
+
+    /// Give the state of the mouse to the script, as `Mouse`
+    pub fn set_mouse(&mut self, mouse: &Mouse) {
+        let result = self.script.get_state().globals().set("Mouse", mouse.clone());
+
+        if let Err(err) = result {
+            self.failed(err);
+        }
+    }
+
+    /// Camera of the gameobject, the size of the window or of its render target is updated for the lua helpers
+    pub fn camera(&mut self, window: Rect, targets: &HashMap<String, Rect>) -> Option<Camera> {
+        let result = self.get_component::<Camera>().and_then(|camera| match camera {
+            Some(mut camera) => {
+                let screen = camera.target.as_ref().and_then(|target| targets.get(target)).copied().unwrap_or(window);
+                camera.screen = Region { x: screen.x, y: screen.y, w: screen.w, h: screen.h };
+                self.write_component_fields::<Camera>(|table| write_component(&camera.screen, table, "screen"))?;
+                Ok(Some(camera))
+            },
+            None => Ok(None)
+        });
 
-                if let Some(texture_str) = material.texture {
-                    texture = asset_loader.get_texture(&texture_str);
-                };
-                
-            } else if component.c_type == "Transform" {
-                let transform = Transform::from_lua_table(component.data).unwrap();
+        match result {
+            Ok(camera) => camera,
+            Err(err) => {
+                self.failed(err);
+                None
+            }
+        }
+    }
+
+    /// Render target of the gameobject and the name of its texture
+    pub fn render_target(&mut self) -> Option<(String, RenderTarget)> {
+        match self.get_component::<RenderTarget>() {
+            Ok(target) => target.map(|target| (target.name.clone().unwrap_or_else(|| self.id.clone()), target)),
+            Err(err) => {
+                self.failed(err);
+                None
+            }
+        }
+    }
+
+    pub fn render(&mut self, ctx: &ggez::Context, renderer: &mut Renderer, asset_loader: &AssetsLoader) {
+        if let Err(err) = self.draw(renderer, asset_loader).and_then(|order| self.draw_immediate(ctx, renderer, asset_loader, order)).and_then(|_| self.draw_debug()) {
+            self.failed(err);
+        }
+    }
+
+    /// Show the origin, the axes, the quad and the name of the gameobject on the debug overlay
+    fn draw_debug(&self) -> mlua::Result<()> {
+        let mut debug = self.debug.borrow_mut();
 
-                draw_param = draw_param
+        if !debug.is_overlay_visible() {
+            return Ok(());
+        }
+
+        let transform = self.transform()?;
+        let position = transform.position.to();
+
+        if let Some(quad) = self.bounds.get() {
+            let corners = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y].map(|corner| quad.transform_point3(corner.extend(0.0)).truncate());
+            debug.push_polygon(&corners, Color::YELLOW);
+        }
+
+        debug.push_axes(position, transform.rotation);
+        debug.push(DebugCommand::Text { text: self.id.clone(), position: position + Vec2::new(4.0, 4.0), color: Color::WHITE });
+
+        Ok(())
+    }
+
+    /// Give the lights of the gameobject to the renderer, true when it has one
+    fn push_lights(&self, renderer: &mut Renderer, transform: &Transform) -> mlua::Result<bool> {
+        let ambient = self.get_component::<AmbientLight>()?;
+        let point = self.get_component::<PointLight>()?;
+
+        if let Some(light) = &ambient {
+            let color = light.color.to();
+            renderer.push_ambient_light(Color::new(color.r * light.intensity, color.g * light.intensity, color.b * light.intensity, 1.0));
+        }
+
+        if let Some(light) = &point {
+            let color = light.color.to();
+
+            renderer.push_light(LightDraw {
+                position: transform.position.to(),
+                height: light.height,
+                radius: light.radius,
+                falloff: light.falloff,
+                color: Color::new(color.r * light.intensity, color.g * light.intensity, color.b * light.intensity, 1.0)
+            });
+        }
+
+        Ok(ambient.is_some() || point.is_some())
+    }
+
+    /// Call the `Draw` function of the script, if any, and draw what it requested with `Graphics`
+    fn draw_immediate(&self, ctx: &ggez::Context, renderer: &mut Renderer, asset_loader: &AssetsLoader, order: RenderOrder) -> mlua::Result<()> {
+        let draw = match self.script.get_state().globals().get::<_, Option<Function>>("Draw")? {
+            Some(draw) => draw,
+            None => return Ok(())
+        };
+
+        let result = draw.call::<_, ()>(());
+        // Drained even on error, so the next call does not start with a pushed transform
+        let commands = self.graphics.borrow_mut().drain();
+        result?;
+
+        for command in commands {
+            match command {
+                GraphicsCommand::Shapes { vertices, indices } => {
+                    let mesh = Mesh::from_data(ctx, MeshData { vertices: &vertices, indices: &indices });
+                    renderer.push_mesh(order, MeshDraw {
+                        mesh,
+                        texture: None,
+                        normal_map: None,
+                        shader: None,
+                        blend: BlendMode::ALPHA,
+                        param: DrawParam::default()
+                    });
+                },
+                GraphicsCommand::Image { texture, region, transform, color } => {
+                    // The texture can be missing for a frame, while the assets are loading
+                    let image = match asset_loader.get_texture(&texture) {
+                        Some(image) => image,
+                        None => continue
+                    };
+
+                    let (width, height) = (image.width() as f32, image.height() as f32);
+                    let region = region.unwrap_or(Rect::new(0.0, 0.0, width, height));
+
+                    renderer.push_sprite(order, SpriteDraw {
+                        texture: Some(texture),
+                        uv: Rect::new(region.x / width, region.y / height, region.w / width, region.h / height),
+                        transform: transform * Mat4::from_scale(Vec3::new(region.w, region.h, 1.0)),
+                        color,
+                        shader: None,
+                        blend: BlendMode::ALPHA,
+                        normal_map: None
+                    });
+                },
+                GraphicsCommand::Text { text, transform } => push_text(renderer, order, transform, &text, asset_loader)
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Transform of the gameobject, at the origin of the world when it has none
+    fn transform(&self) -> mlua::Result<Transform> {
+        Ok(self.get_component::<Transform>()?.unwrap_or(Transform {
+            position: Vector { x: 0.0, y: 0.0 },
+            rotation: 0.0,
+            scale: Vector { x: 1.0, y: 1.0 }
+        }))
+    }
+
+    /// Draw the components of the gameobject, return its render order
+    fn draw(&self, renderer: &mut Renderer, asset_loader: &AssetsLoader) -> mlua::Result<RenderOrder> {
+        self.bounds.set(None);
+
+        let mut color = Color::WHITE;
+        let mut texture : Option<String> = None;
+        let mut shader : Option<ShaderDraw> = None;
+        let mut blend = BlendMode::ALPHA;
+        let mut normal_map : Option<String> = None;
+
+        if let Some(material) = self.get_component::<Material>()? {
+            color = material.albedo.to();
+            shader = shader_draw(&material, asset_loader)?;
+            blend = blend_mode(&material.blend).map_err(mlua::Error::RuntimeError)?;
+
+            texture = material.texture;
+            normal_map = material.normal_map;
+        }
+
+        let transform = self.transform()?;
+
+        let order = renderer.order(self.get_component::<Layer>()?.as_ref(), transform.position.y)?;
+        let has_light = self.push_lights(renderer, &transform)?;
+
+        if let Some(tilemap) = self.get_component::<Tilemap>()? {
+            // The map can be missing for a frame, while the assets are loading
+            if let Some(map) = tilemap.map.as_ref().and_then(|map| asset_loader.get_tilemap(map)) {
+                let draw_param = DrawParam::default()
+                    .color(color)
                     .scale(transform.scale.to())
                     .rotation(transform.rotation)
                     .dest(transform.position.to());
+
+                for layer in map.get_meshes().iter() {
+                    renderer.push_mesh(order, MeshDraw {
+                        mesh: layer.mesh.clone(),
+                        texture: Some(layer.texture.clone()),
+                        normal_map: normal_map.clone(),
+                        shader: shader.clone(),
+                        blend,
+                        param: draw_param
+                    });
+                }
             }
+
+            return Ok(order);
         }
 
-        match texture {
-            Some(texture) => canvas.draw_textured_mesh(mesh, texture.clone(), draw_param),
-            None => canvas.draw(&mesh, draw_param),
+        let sprite = self.get_component::<Sprite>()?;
+        let text = self.get_component::<Text>()?;
+
+        if let Some(emitter) = self.get_component::<ParticleEmitter>()? {
+            if let Some(settings) = emitter_settings(&emitter, asset_loader) {
+                for particle in self.particles.borrow().draws(settings, transform.position.to()) {
+                    renderer.push_sprite(order, SpriteDraw {
+                        texture: settings.texture.clone(),
+                        uv: Rect::new(0.0, 0.0, 1.0, 1.0),
+                        transform: sprite_transform(particle.position, 0.0, Vec2::ONE, Vec2::new(0.5, 0.5), Vec2::splat(particle.size)),
+                        color: particle.color,
+                        shader: shader.clone(),
+                        blend,
+                        normal_map: None
+                    });
+                }
+            }
+
+            // Particles alone do not draw the default square
+            if sprite.is_none() && text.is_none() {
+                return Ok(order);
+            }
+        }
+
+        // Lights alone do not draw the default square
+        if has_light && sprite.is_none() && text.is_none() {
+            return Ok(order);
+        }
+
+        // A text alone does not draw the default square
+        if let (None, Some(text)) = (&sprite, &text) {
+            push_text(renderer, order, text_origin(&transform), text, asset_loader);
+            return Ok(order);
         }
+
+        let (texture, uv, size, pivot, slice) = match sprite {
+            Some(sprite) => {
+                let mut region = sprite.region.as_ref().map(|region| region.to());
+                let mut pivot = sprite.pivot.to();
+
+                if let Some(sheet_name) = &sprite.sheet {
+                    // The sheet can be missing for a frame, while the assets are loading
+                    if let Some(sheet) = asset_loader.get_sprite_sheet(sheet_name) {
+                        texture = Some(sheet.get_image().clone());
+
+                        if let Some(frame_name) = &sprite.frame {
+                            match sheet.get_frame(frame_name) {
+                                Some(frame) => {
+                                    region = Some(frame.region);
+                                    pivot = frame.pivot(pivot, sprite.flip_x, sprite.flip_y);
+                                },
+                                None => return Err(mlua::Error::RuntimeError(format!("Unknown frame {} in sprite sheet {}", frame_name, sheet_name)))
+                            }
+                        }
+                    }
+                }
+
+                let image = texture.as_ref().and_then(|texture| asset_loader.get_texture(texture));
+                let (uv, size) = sprite_region(&sprite, region, image);
+
+                // Size of the region in the texture, the borders of a nine slice sprite are in its pixels
+                let source = image.map(|image| Vec2::new(uv.w.abs() * image.width() as f32, uv.h.abs() * image.height() as f32)).unwrap_or(size);
+
+                (texture, uv, size, pivot, sprite.slice.map(|borders| (borders, source)))
+            },
+            None => (texture, Rect::new(0.0, 0.0, 1.0, 1.0), Vec2::new(32.0, 32.0), Vec2::new(0.5, 0.5), None)
+        };
+
+        let quad = sprite_transform(transform.position.to(), transform.rotation, transform.scale.to(), pivot, size);
+        self.bounds.set(Some(quad));
+
+        match slice {
+            Some((borders, source)) => {
+                let size = size * transform.scale.to().abs();
+
+                for slice in nine_slice(slice_origin(&transform, pivot, size), uv, source, size, &borders) {
+                    renderer.push_sprite(order, SpriteDraw {
+                        texture: texture.clone(),
+                        uv: slice.uv,
+                        transform: slice.transform,
+                        color,
+                        shader: shader.clone(),
+                        blend,
+                        normal_map: normal_map.clone()
+                    });
+                }
+            },
+            None => renderer.push_sprite(order, SpriteDraw {
+                texture,
+                uv,
+                transform: quad,
+                color,
+                shader,
+                blend,
+                normal_map
+            })
+        }
+
+        if let Some(text) = &text {
+            push_text(renderer, order, text_origin(&transform), text, asset_loader);
+        }
+
+        Ok(order)
     }
 }
 
-pub fn rect(ctx: &ggez::Context, rect: Rect) -> graphics::Mesh {
-    let vertices = vec![
-        graphics::Vertex { 
-            position: [rect.x, rect.y], 
-            uv: [0.0, 0.0], 
-            color: [1.0, 1.0, 1.0, 1.0] 
-        },
-        graphics::Vertex { 
-            position: [rect.x + rect.w, rect.y], 
-            uv: [1.0, 0.0], 
-            color: [1.0, 1.0, 1.0, 1.0] 
-        },
-        graphics::Vertex { 
-            position: [rect.x + rect.w, rect.y + rect.h], 
-            uv: [1.0, 1.0], 
-            color: [1.0, 1.0, 1.0, 1.0] 
-        },
-        graphics::Vertex { 
-            position: [rect.x, rect.y + rect.h], 
-            uv: [0.0, 1.0], 
-            color: [1.0, 1.0, 1.0, 1.0] 
-        }
-    ];
-
-    let indices = vec![0,1,2,2,3,0];
-
-    graphics::Mesh::from_data(ctx, graphics::MeshData { vertices: &vertices, indices: &indices })
-}
\ No newline at end of file
+/// Draw a text, `transform` place its origin
+fn push_text(renderer: &mut Renderer, order: RenderOrder, transform: Mat4, text: &Text, asset_loader: &AssetsLoader) {
+    // The font can be missing for a frame, while the assets are loading
+    if text.font.as_ref().is_some_and(|font| !asset_loader.has_font(font)) {
+        return;
+    }
+
+    if let Some(font) = text.font.as_ref().and_then(|font| asset_loader.get_bitmap_font(font)) {
+        let scale = font.scale(text.size);
+        let color = text.color.to();
+
+        for glyph in font.layout(text, scale) {
+            let page = &font.get_pages()[glyph.page];
+
+            if let Some(image) = asset_loader.get_texture(page) {
+                let (width, height) = (image.width() as f32, image.height() as f32);
+
+                renderer.push_sprite(order, SpriteDraw {
+                    texture: Some(page.clone()),
+                    uv: Rect::new(glyph.region.x / width, glyph.region.y / height, glyph.region.w / width, glyph.region.h / height),
+                    transform: glyph_transform(transform, scale, &glyph),
+                    color,
+                    shader: None,
+                    blend: BlendMode::ALPHA,
+                    normal_map: None
+                });
+            }
+        }
+
+        return;
+    }
+
+    renderer.push_text(order, text.build(), DrawParam::default()
+        .color(text.color.to())
+        .transform(transform));
+}
+
+/// Shader of a material with the values of its uniforms, none while the shader is loading
+fn shader_draw(material: &Material, asset_loader: &AssetsLoader) -> mlua::Result<Option<ShaderDraw>> {
+    let name = match &material.shader {
+        Some(name) => name,
+        None => return Ok(None)
+    };
+
+    let shader = match asset_loader.get_shader(name) {
+        Some(shader) => shader,
+        None => return Ok(None)
+    };
+
+    let uniforms = shader.uniforms(&material.uniforms)
+        .map_err(|err| mlua::Error::RuntimeError(format!("Invalid uniforms for shader {} : {}", name, err)))?;
+
+    Ok(Some(ShaderDraw { name: name.clone(), uniforms }))
+}
+
+/// Origin of the text component, snapped to a whole pixel so glyphs stay sharp
+fn text_origin(transform: &Transform) -> Mat4 {
+    Mat4::from_translation(transform.position.to().round().extend(0.0))
+        * Mat4::from_rotation_z(transform.rotation)
+        * Mat4::from_scale(transform.scale.to().extend(1.0))
+}
+
+/// Part of the texture drawn by a sprite in uv coordinates and its size in pixels, region is in pixels and default to the whole texture
+pub fn sprite_region(sprite: &Sprite, region: Option<Rect>, texture: Option<&ggez::graphics::Image>) -> (Rect, Vec2) {
+    let (texture_width, texture_height) = match texture {
+        Some(texture) => (texture.width() as f32, texture.height() as f32),
+        None => (32.0, 32.0)
+    };
+
+    let region = region.unwrap_or(Rect::new(0.0, 0.0, texture_width, texture_height));
+    let size = match &sprite.size {
+        Some(size) => size.to(),
+        None => Vec2::new(region.w, region.h)
+    };
+
+    let mut uv = Rect::new(region.x / texture_width, region.y / texture_height, region.w / texture_width, region.h / texture_height);
+
+    if sprite.flip_x {
+        uv.x += uv.w;
+        uv.w = -uv.w;
+    }
+
+    if sprite.flip_y {
+        uv.y += uv.h;
+        uv.h = -uv.h;
+    }
+
+    (uv, size)
+}
@@ -0,0 +1,199 @@
+use std::{cell::RefCell, rc::Rc};
+
+use ggez::{glam::{Mat4, Vec2, Vec3}, graphics::{Color, DrawMode, MeshBuilder, Rect, Vertex}};
+use mlua::{UserData, Variadic};
+
+use crate::components::{Region, Text};
+
+pub type GraphicsStorage = Rc<RefCell<Graphics>>;
+
+/// Tolerance used to build circles, in pixels
+const CIRCLE_TOLERANCE: f32 = 0.1;
+
+/// Drawing requested by the `Draw` function of a script, in world coordinates
+pub enum GraphicsCommand {
+    /// Consecutive shapes, already transformed and colored
+    Shapes { vertices: Vec<Vertex>, indices: Vec<u32> },
+    /// Region of a texture (the whole texture when none), `transform` does not include the size of the image
+    Image { texture: String, region: Option<Rect>, transform: Mat4, color: Color },
+    Text { text: Text, transform: Mat4 }
+}
+
+/// State of the immediate mode drawing of a gameobject, commands are drained after each `Draw` call
+pub struct Graphics {
+    commands: Vec<GraphicsCommand>,
+    transform: Mat4,
+    stack: Vec<Mat4>,
+    color: Color,
+    line_width: f32
+}
+
+impl Graphics {
+    pub fn new() -> Self {
+        Self { commands: Vec::new(), transform: Mat4::IDENTITY, stack: Vec::new(), color: Color::WHITE, line_width: 1.0 }
+    }
+
+    /// Commands of the last `Draw` call, the transform and the color are reset for the next one
+    pub fn drain(&mut self) -> Vec<GraphicsCommand> {
+        self.transform = Mat4::IDENTITY;
+        self.stack.clear();
+        self.color = Color::WHITE;
+        self.line_width = 1.0;
+
+        std::mem::take(&mut self.commands)
+    }
+
+    fn push_shape(&mut self, builder: &MeshBuilder) {
+        let data = builder.build();
+
+        let vertices = data.vertices.iter().map(|vertex| {
+            let position = self.transform.transform_point3(Vec3::new(vertex.position[0], vertex.position[1], 0.0));
+            Vertex { position: [position.x, position.y], uv: vertex.uv, color: vertex.color }
+        });
+
+        if let Some(GraphicsCommand::Shapes { vertices: shape_vertices, indices }) = self.commands.last_mut() {
+            let offset = shape_vertices.len() as u32;
+
+            shape_vertices.extend(vertices);
+            indices.extend(data.indices.iter().map(|index| index + offset));
+        } else {
+            self.commands.push(GraphicsCommand::Shapes { vertices: vertices.collect(), indices: data.indices.to_vec() });
+        }
+    }
+
+    fn draw_mode(&self, mode: Option<String>) -> mlua::Result<DrawMode> {
+        match mode.as_deref() {
+            None | Some("fill") => Ok(DrawMode::fill()),
+            Some("line") => Ok(DrawMode::stroke(self.line_width)),
+            Some(other) => Err(mlua::Error::RuntimeError(format!("{} is not a draw mode, expected fill or line", other)))
+        }
+    }
+}
+
+/// Points written as `x1, y1, x2, y2, ...`
+fn points(coordinates: &[f32]) -> mlua::Result<Vec<Vec2>> {
+    if !coordinates.len().is_multiple_of(2) {
+        return Err(mlua::Error::RuntimeError(String::from("Points need an even number of coordinates")));
+    }
+
+    Ok(coordinates.chunks(2).map(|point| Vec2::new(point[0], point[1])).collect())
+}
+
+fn to_lua_error(err: ggez::GameError) -> mlua::Error {
+    mlua::Error::RuntimeError(err.to_string())
+}
+
+/// Lua side handle of the immediate mode drawing, available as `rust_graphics`
+pub struct LuaGraphics {
+    graphics: GraphicsStorage
+}
+
+impl LuaGraphics {
+    pub fn new(graphics: GraphicsStorage) -> Self {
+        Self { graphics }
+    }
+}
+
+impl UserData for LuaGraphics {
+    fn add_methods<'lua, M: mlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("set_color", |_, this, (r, g, b, a): (f32, f32, f32, Option<f32>)| {
+            this.graphics.borrow_mut().color = Color::new(r / 255.0, g / 255.0, b / 255.0, a.unwrap_or(255.0) / 255.0);
+            Ok(())
+        });
+
+        methods.add_method("set_line_width", |_, this, width: f32| {
+            this.graphics.borrow_mut().line_width = width;
+            Ok(())
+        });
+
+        methods.add_method("rect", |_, this, (x, y, w, h, mode): (f32, f32, f32, f32, Option<String>)| {
+            let mut graphics = this.graphics.borrow_mut();
+            let mut builder = MeshBuilder::new();
+
+            builder.rectangle(graphics.draw_mode(mode)?, Rect::new(x, y, w, h), graphics.color).map_err(to_lua_error)?;
+            graphics.push_shape(&builder);
+            Ok(())
+        });
+
+        methods.add_method("circle", |_, this, (x, y, radius, mode): (f32, f32, f32, Option<String>)| {
+            let mut graphics = this.graphics.borrow_mut();
+            let mut builder = MeshBuilder::new();
+
+            builder.circle(graphics.draw_mode(mode)?, Vec2::new(x, y), radius, CIRCLE_TOLERANCE, graphics.color).map_err(to_lua_error)?;
+            graphics.push_shape(&builder);
+            Ok(())
+        });
+
+        methods.add_method("line", |_, this, coordinates: Variadic<f32>| {
+            let mut graphics = this.graphics.borrow_mut();
+            let mut builder = MeshBuilder::new();
+
+            builder.line(&points(&coordinates)?, graphics.line_width, graphics.color).map_err(to_lua_error)?;
+            graphics.push_shape(&builder);
+            Ok(())
+        });
+
+        methods.add_method("polygon", |_, this, (mode, coordinates): (Option<String>, Variadic<f32>)| {
+            let mut graphics = this.graphics.borrow_mut();
+            let mut builder = MeshBuilder::new();
+
+            builder.polygon(graphics.draw_mode(mode)?, &points(&coordinates)?, graphics.color).map_err(to_lua_error)?;
+            graphics.push_shape(&builder);
+            Ok(())
+        });
+
+        methods.add_method("image", |_, this, (texture, x, y, region): (String, f32, f32, Option<Region>)| {
+            let mut graphics = this.graphics.borrow_mut();
+            let transform = graphics.transform * Mat4::from_translation(Vec3::new(x, y, 0.0));
+            let color = graphics.color;
+
+            graphics.commands.push(GraphicsCommand::Image { texture, region: region.map(|region| region.to()), transform, color });
+            Ok(())
+        });
+
+        methods.add_method("text", |_, this, (mut text, x, y): (Text, f32, f32)| {
+            let mut graphics = this.graphics.borrow_mut();
+            let transform = graphics.transform * Mat4::from_translation(Vec3::new(x, y, 0.0));
+
+            let color = graphics.color;
+            text.color = crate::components::Color { r: color.r * 255.0, g: color.g * 255.0, b: color.b * 255.0, a: color.a * 255.0 };
+
+            graphics.commands.push(GraphicsCommand::Text { text, transform });
+            Ok(())
+        });
+
+        methods.add_method("push", |_, this, ()| {
+            let mut graphics = this.graphics.borrow_mut();
+            let transform = graphics.transform;
+
+            graphics.stack.push(transform);
+            Ok(())
+        });
+
+        methods.add_method("pop", |_, this, ()| {
+            let mut graphics = this.graphics.borrow_mut();
+
+            graphics.transform = graphics.stack.pop()
+                .ok_or_else(|| mlua::Error::RuntimeError(String::from("Graphics:pop called without Graphics:push")))?;
+            Ok(())
+        });
+
+        methods.add_method("translate", |_, this, (x, y): (f32, f32)| {
+            let mut graphics = this.graphics.borrow_mut();
+            graphics.transform *= Mat4::from_translation(Vec3::new(x, y, 0.0));
+            Ok(())
+        });
+
+        methods.add_method("rotate", |_, this, angle: f32| {
+            let mut graphics = this.graphics.borrow_mut();
+            graphics.transform *= Mat4::from_rotation_z(angle);
+            Ok(())
+        });
+
+        methods.add_method("scale", |_, this, (x, y): (f32, Option<f32>)| {
+            let mut graphics = this.graphics.borrow_mut();
+            graphics.transform *= Mat4::from_scale(Vec3::new(x, y.unwrap_or(x), 1.0));
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,102 @@
+use crevice::std140::AsStd140;
+use ggez::{glam::{Mat4, Vec2, Vec3}, graphics::{self, Image, Shader, ShaderBuilder}, mint::{ColumnMatrix4, Vector4}, GameResult};
+
+/// Lights drawn at once, the other lights of the frame are ignored
+pub const MAX_LIGHTS: usize = 16;
+
+const LIGHTING_SHADER: &str = include_str!("lighting.wgsl");
+
+/// Point light in world coordinates, its color is multiplied by its intensity
+#[derive(Clone, Debug)]
+pub struct LightDraw {
+    pub position: Vec2,
+    pub height: f32,
+    pub radius: f32,
+    pub falloff: f32,
+    pub color: graphics::Color
+}
+
+/// Lights of the frame, lit layers are only drawn with the lighting shader when the scene has a light
+#[derive(Default)]
+pub struct Lighting {
+    /// Sum of the ambient lights, black when there is none
+    pub ambient: Option<graphics::Color>,
+    pub lights: Vec<LightDraw>
+}
+
+/// Lights as sent to the gpu, see `lighting.wgsl`. Each matrix holds two lights
+#[derive(AsStd140)]
+pub struct LightUniforms {
+    ambient: Vector4<f32>,
+    count: Vector4<f32>,
+    a: ColumnMatrix4<f32>,
+    b: ColumnMatrix4<f32>,
+    c: ColumnMatrix4<f32>,
+    d: ColumnMatrix4<f32>,
+    e: ColumnMatrix4<f32>,
+    f: ColumnMatrix4<f32>,
+    g: ColumnMatrix4<f32>,
+    h: ColumnMatrix4<f32>
+}
+
+impl Lighting {
+    pub fn is_active(&self) -> bool {
+        self.ambient.is_some() || !self.lights.is_empty()
+    }
+
+    pub fn add_ambient(&mut self, color: graphics::Color) {
+        let ambient = self.ambient.unwrap_or(graphics::Color::BLACK);
+        self.ambient = Some(graphics::Color::new(ambient.r + color.r, ambient.g + color.g, ambient.b + color.b, 1.0));
+    }
+
+    pub fn clear(&mut self) {
+        self.ambient = None;
+        self.lights.clear();
+    }
+
+    /// Uniforms of the lights seen through `view`, the transform from the world to the pixels of the target
+    pub fn uniforms(&self, view: Mat4) -> LightUniforms {
+        let mut lights = [0.0; MAX_LIGHTS * 8];
+        // Radius and height follow the zoom of the view
+        let zoom = view.transform_vector3(Vec3::X).length();
+
+        for (index, light) in self.lights.iter().take(MAX_LIGHTS).enumerate() {
+            let position = view.transform_point3(light.position.extend(0.0));
+
+            lights[index * 8..(index + 1) * 8].copy_from_slice(&[
+                position.x, position.y, light.height * zoom, light.radius * zoom,
+                light.color.r, light.color.g, light.color.b, light.falloff
+            ]);
+        }
+
+        let matrix = |index: usize| {
+            let mut columns = [0.0; 16];
+            columns.copy_from_slice(&lights[index * 16..(index + 1) * 16]);
+            ColumnMatrix4::from(columns)
+        };
+
+        let ambient = self.ambient.unwrap_or(graphics::Color::BLACK);
+
+        LightUniforms {
+            ambient: Vector4::from([ambient.r, ambient.g, ambient.b, 1.0]),
+            count: Vector4::from([self.lights.len().min(MAX_LIGHTS) as f32, 0.0, 0.0, 0.0]),
+            a: matrix(0),
+            b: matrix(1),
+            c: matrix(2),
+            d: matrix(3),
+            e: matrix(4),
+            f: matrix(5),
+            g: matrix(6),
+            h: matrix(7)
+        }
+    }
+}
+
+pub fn lighting_shader(ctx: &ggez::Context) -> GameResult<Shader> {
+    ShaderBuilder::new().fragment_code(LIGHTING_SHADER).build(ctx)
+}
+
+/// Normal map of the textures without one, every pixel faces the camera
+pub fn flat_normal_map(ctx: &ggez::Context) -> Image {
+    Image::from_color(ctx, 1, 1, Some(graphics::Color::new(0.5, 0.5, 1.0, 1.0)))
+}
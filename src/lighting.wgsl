@@ -0,0 +1,52 @@
+// Lighting of the sprites and meshes of lit layers, the vertex shader is the one of ggez
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+// Each light takes two vectors : position on the target (x, y), height and radius in pixels, then color and falloff
+struct Lighting {
+    ambient: vec4<f32>,
+    count: vec4<f32>,
+    lights: array<vec4<f32>, 32>,
+}
+
+@group(1) @binding(0)
+var t: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s: sampler;
+
+@group(3) @binding(0)
+var<uniform> lighting: Lighting;
+
+@group(3) @binding(1)
+var normal_map: texture_2d<f32>;
+
+@group(3) @binding(2)
+var normal_sampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let albedo = in.color * textureSample(t, s, in.uv);
+    let encoded = textureSample(normal_map, normal_sampler, in.uv).xyz * 2.0 - 1.0;
+    // Normal maps point their green channel up, the y axis of the target points down
+    let normal = normalize(vec3<f32>(encoded.x, -encoded.y, encoded.z));
+
+    var light = lighting.ambient.rgb;
+
+    for (var i = 0u; i < u32(lighting.count.x); i = i + 1u) {
+        let position = lighting.lights[i * 2u];
+        let color = lighting.lights[i * 2u + 1u];
+
+        let offset = vec3<f32>(position.xy - in.position.xy, position.z);
+        let attenuation = pow(clamp(1.0 - length(offset.xy) / position.w, 0.0, 1.0), color.a);
+        let diffuse = max(dot(normal, normalize(offset)), 0.0);
+
+        light = light + color.rgb * attenuation * diffuse;
+    }
+
+    return vec4<f32>(albedo.rgb * light, albedo.a);
+}
@@ -1,53 +1,72 @@
-use std::{error::Error, marker::PhantomData};
+use std::{collections::HashSet, error::Error, marker::PhantomData, path::Path, sync::mpsc::{channel, Receiver}};
 
 use mlua::{FromLua, ToLua, Function};
+use notify::{Watcher, EventKind, event::{AccessKind, AccessMode}};
 
-#[derive(std::default::Default)]
-pub struct Empty {}
+pub use pebble_derive::LuaComponent;
 
 pub trait LuaComponent<'lua> {
-    fn from_lua_table(table: mlua::Table<'lua>) -> Result<Self, Box<dyn Error>> where Self: Sized;
+    fn from_lua_table(table: mlua::Table<'lua>) -> mlua::Result<Self> where Self: Sized;
+
+    /// Write every field into an existing table, so scripts holding the table see the new values
+    fn write_to_table(&self, table: &mlua::Table<'lua>) -> mlua::Result<()>;
 }
 
-#[macro_export]
-macro_rules! user_data {
-    (
-        #[derive($($derived: ident),*)]
-        $struct_access: vis struct $ty: ident<'a> {
-            $($field_access: vis $value: ident: $value_type: ty),*
-        }
-    ) => {
-        #[derive($($derived),*)]
-        $struct_access struct $ty<'a> {
-            $($field_access $value: $value_type),*,
-            phantom: &'a std::marker::PhantomData<crate::lua::Empty>
-        }
+/// Field written back to lua by `#[derive(LuaComponent)]`. The write is picked by autoref on `&&&FieldWriter(&field)` :
+/// nested components are written into the table already in the field, so scripts holding it see the new values,
+/// other values replace the field.
+pub struct FieldWriter<'a, T>(pub &'a T);
 
-        impl<'lua> mlua::FromLua<'lua> for $ty<'lua> {
-            fn from_lua(lua_value: mlua::Value<'lua>, lua: &'lua mlua::Lua) -> mlua::Result<Self> {
-                let table = match lua_value {
-                    mlua::Value::Table(table) => Ok(table),
-                    mlua::Value::Error(err) => Err(err),
-                    mlua::Value::Nil => Err(mlua::Error::RuntimeError(String::from("Value is Nil"))),
-                    _ => Err(mlua::Error::RuntimeError(String::from("Only table are supported")))
-                }.unwrap();
-        
-                Ok(Self {
-                    $($value: table.get(stringify!($value)).unwrap()),*,
-                    phantom: &std::marker::PhantomData
-                })
-            }
-        }
+/// Update the component table of the field in place, set it when the field has no table
+pub fn write_component<'lua, T: LuaComponent<'lua> + ToLua<'lua> + Clone>(component: &T, table: &mlua::Table<'lua>, name: &str) -> mlua::Result<()> {
+    match table.get::<_, mlua::Value>(name)? {
+        mlua::Value::Table(existing) => component.write_to_table(&existing),
+        _ => table.set(name, component.clone())
+    }
+}
 
-        impl<'lua> crate::lua::LuaComponent<'lua> for $ty<'lua> {
-            fn from_lua_table(table: mlua::Table<'lua>) -> Result<Self, Box<dyn Error>> {
-                Ok(Self {
-                    $($value: table.get(stringify!($value))?),*,
-                    phantom: &std::marker::PhantomData,
-                })
-            }
+pub trait WriteComponentField<'lua> {
+    fn write_field(&self, table: &mlua::Table<'lua>, name: &str) -> mlua::Result<()>;
+}
+
+impl<'lua, 'a, T: LuaComponent<'lua> + ToLua<'lua> + Clone> WriteComponentField<'lua> for &&FieldWriter<'a, T> {
+    fn write_field(&self, table: &mlua::Table<'lua>, name: &str) -> mlua::Result<()> {
+        write_component(self.0, table, name)
+    }
+}
+
+pub trait WriteOptionalComponentField<'lua> {
+    fn write_field(&self, table: &mlua::Table<'lua>, name: &str) -> mlua::Result<()>;
+}
+
+impl<'lua, 'a, T: LuaComponent<'lua> + ToLua<'lua> + Clone> WriteOptionalComponentField<'lua> for &FieldWriter<'a, Option<T>> {
+    fn write_field(&self, table: &mlua::Table<'lua>, name: &str) -> mlua::Result<()> {
+        match self.0 {
+            Some(component) => write_component(component, table, name),
+            None => table.set(name, mlua::Value::Nil)
         }
-    };
+    }
+}
+
+pub trait WriteValueField<'lua> {
+    fn write_field(&self, table: &mlua::Table<'lua>, name: &str) -> mlua::Result<()>;
+}
+
+impl<'lua, 'a, T: ToLua<'lua> + Clone> WriteValueField<'lua> for FieldWriter<'a, T> {
+    fn write_field(&self, table: &mlua::Table<'lua>, name: &str) -> mlua::Result<()> {
+        table.set(name, self.0.clone())
+    }
+}
+
+/// Field of a struct exposed to lua, used to generate the lua language server definitions
+pub struct LuaField {
+    pub name: &'static str,
+    pub rust_type: &'static str
+}
+
+pub trait LuaDefinition {
+    fn lua_name() -> &'static str;
+    fn lua_fields() -> Vec<LuaField>;
 }
 
 pub struct LuaArray<'a, T : mlua::FromLua<'a> + Clone> {
@@ -57,7 +76,7 @@ pub struct LuaArray<'a, T : mlua::FromLua<'a> + Clone> {
 }
 
 impl<'a, T : mlua::FromLua<'a> + Clone> LuaArray<'a, T> {
-    pub fn iter(&self) -> mlua::TablePairs<'_, String, T> {
+    pub fn iter(&self) -> mlua::TablePairs<'a, String, T> {
         self.data.clone().pairs::<std::string::String, T>()
     }
 }
@@ -124,4 +143,61 @@ impl Script {
         let func : Function = self.get(name)?;
         Ok(func.call::<_, T>(())?)
     }
-}
\ No newline at end of file
+}
+
+/// Single watcher for the scripts of every gameobject, a script run by many gameobjects is only watched once
+pub struct ScriptWatcher {
+    scripts: HashSet<String>,
+    receiver: Receiver<Result<notify::Event, notify::Error>>,
+    watcher: Option<notify::RecommendedWatcher>
+}
+
+impl ScriptWatcher {
+    pub fn new() -> Self {
+        let (sender, receiver) = channel();
+        let watcher = notify::recommended_watcher(sender);
+
+        if let Err(err) = &watcher {
+            warn!("Scripts hot reload is disabled : {}", err);
+        }
+
+        Self { scripts: HashSet::new(), receiver, watcher: watcher.ok() }
+    }
+
+    pub fn watch(&mut self, filename: &str) {
+        if !self.scripts.insert(filename.to_string()) {
+            return;
+        }
+
+        if let Some(watcher) = &mut self.watcher {
+            if let Err(err) = watcher.watch(Path::new(filename), notify::RecursiveMode::NonRecursive) {
+                error!("Cannot watch script {} : {}", filename, err);
+            }
+        }
+    }
+
+    /// Scripts written since the last call
+    pub fn changed(&self) -> HashSet<String> {
+        let mut changed = HashSet::new();
+
+        while let Ok(event) = self.receiver.try_recv() {
+            let event = match event {
+                Ok(event) => event,
+                Err(err) => {
+                    error!("Error when watching scripts : {}", err);
+                    continue;
+                }
+            };
+
+            if event.kind != EventKind::Access(AccessKind::Close(AccessMode::Write)) {
+                continue;
+            }
+
+            for path in event.paths.iter() {
+                changed.extend(self.scripts.iter().filter(|script| path.ends_with(script)).cloned());
+            }
+        }
+
+        changed
+    }
+}
@@ -4,19 +4,38 @@ extern crate log;
 use chrono::Utc;
 use env_logger::{Builder, Logger};
 
-use ggez::glam::Vec2;
 use log::{SetLoggerError, Log, Level};
 use lua::Script;
 use mlua::UserData;
 use once_cell::sync::Lazy;
 use core::Core;
-use std::{error::Error, marker::PhantomData};
 
 mod core;
 mod gameobject;
 mod lua;
 mod assets;
 mod state;
+mod events;
+mod definitions;
+mod components;
+mod sprite_sheet;
+mod animation;
+mod aseprite;
+mod tilemap;
+mod renderer;
+mod project;
+mod camera;
+mod text;
+mod bitmap_font;
+mod graphics;
+mod shader;
+mod render_target;
+mod resolution;
+mod particles;
+mod nine_slice;
+mod lighting;
+mod debug;
+mod capture;
 
 pub enum PebbleMode {
     Release,
@@ -44,49 +63,6 @@ impl Config {
  
 static PEBBLE_CONFIG: Lazy<Config> = Lazy::new(|| Config::new());
 
-user_data!(
-    #[derive(Clone, Debug)]
-    pub struct Vector<'a> {
-        x: f32,
-        y: f32
-    }
-);
-
-user_data!(
-    #[derive(Clone, Debug)]
-    pub struct Transform<'a> {
-        position: Vector<'a>,
-        rotation: f32,
-        scale: Vector<'a>
-    }
-);
-
-user_data!(
-    #[derive(Clone, Debug)]
-    pub struct Color<'a> {
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32
-    }
-);
-
-user_data!(
-    #[derive(Clone, Debug)]
-    pub struct Material<'a> {
-        albedo: Color<'a>,
-        texture: Option<String>
-    }
-);
-
-user_data!(
-    #[derive(Clone, Debug)]
-    pub struct Component<'a> {
-        pub c_type: String,
-        pub data: mlua::Table<'a>
-    }
-);
-
 pub struct CoreLogger {
     pub line: u32,
     pub data: String,
@@ -149,22 +125,6 @@ impl Log for MyLogger {
     fn flush(&self) {}
 }
 
-impl<'a> Material<'a> {
-    pub fn new() -> Self {
-        Self {
-            albedo: Color { r: 222.0, g: 222.0, b: 222.0, a: 255.0, phantom: &PhantomData },
-            texture: None,
-            phantom: &PhantomData,
-        }
-    }
-}
-
-impl<'a> Vector<'a> {
-    pub fn to(&self) -> Vec2 {
-        Vec2 { x: self.x, y: self.y }
-    }
-}
-
 struct LuaLog {
     name: String
 }
@@ -200,12 +160,34 @@ fn add_core_library(script: &Script) {
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.get(1).map(|arg| arg.as_str()) == Some("types") {
+        let filename = args.get(2).map(|arg| arg.as_str()).unwrap_or(definitions::DEFAULT_DEFINITIONS_FILE);
+
+        match definitions::write(filename) {
+            Ok(_) => println!("Lua definitions written to {}", filename),
+            Err(err) => {
+                eprintln!("Cannot write lua definitions to {} : {}", filename, err);
+                std::process::exit(1);
+            }
+        }
+
+        return;
+    }
+
     let mut core = Core::new();
 
     MyLogger::init().unwrap();
 
     core.add_gameobject("main.lua");
     core.add_middleware(add_core_library);
+    core.add_middleware(camera::add_camera_library);
+    core.add_middleware(particles::add_particles_library);
+    core.add_system(animation::animate);
+    core.add_system(tilemap::expose_objects);
+    core.add_system(camera::update_camera);
+    core.add_system(particles::update_particles);
 
     trace!("some trace log");
     debug!("some debug log");
@@ -0,0 +1,114 @@
+use ggez::{glam::{Mat4, Vec2, Vec3}, graphics::Rect};
+
+use crate::components::{Borders, Transform};
+
+/// Quad of a nine slice sprite, its transform include its size in pixels
+pub struct Slice {
+    pub uv: Rect,
+    pub transform: Mat4
+}
+
+/// Transform placing the top left corner of a nine slice sprite, `size` is its size on screen.
+/// The scale of the transform resize the sprite instead of stretching it, only its sign flip the sprite.
+pub fn slice_origin(transform: &Transform, pivot: Vec2, size: Vec2) -> Mat4 {
+    let scale = transform.scale.to();
+
+    Mat4::from_translation(transform.position.to().extend(0.0))
+        * Mat4::from_rotation_z(transform.rotation)
+        * Mat4::from_scale(Vec3::new(scale.x.signum(), scale.y.signum(), 1.0))
+        * Mat4::from_translation((-pivot * size).extend(0.0))
+}
+
+/// Start and end of the three parts of an axis, on screen and in the texture region.
+/// Borders are shrunk when the sprite is smaller than them.
+fn axis(start: f32, end: f32, size: f32, source: f32) -> [(f32, f32, f32, f32); 3] {
+    let shrink = if start + end > size { size / (start + end) } else { 1.0 };
+    let (start_size, end_size) = (start * shrink, end * shrink);
+
+    [
+        (0.0, start_size, 0.0, start),
+        (start_size, size - end_size, start, source - end),
+        (size - end_size, size, source - end, source)
+    ]
+}
+
+/// Split a sprite in nine quads, the corners keep their size, the sides are stretched along them and the center in both directions.
+/// `uv` is the region of the texture, `source` its size in pixels and `size` the size of the sprite on screen.
+pub fn nine_slice(origin: Mat4, uv: Rect, source: Vec2, size: Vec2, borders: &Borders) -> Vec<Slice> {
+    let columns = axis(borders.left, borders.right, size.x, source.x);
+    let rows = axis(borders.top, borders.bottom, size.y, source.y);
+
+    let mut slices = Vec::new();
+
+    for (y, bottom, v, v_end) in rows {
+        for (x, right, u, u_end) in columns {
+            if right <= x || bottom <= y {
+                continue;
+            }
+
+            slices.push(Slice {
+                uv: Rect::new(
+                    uv.x + u / source.x * uv.w,
+                    uv.y + v / source.y * uv.h,
+                    (u_end - u) / source.x * uv.w,
+                    (v_end - v) / source.y * uv.h
+                ),
+                transform: origin
+                    * Mat4::from_translation(Vec3::new(x, y, 0.0))
+                    * Mat4::from_scale(Vec3::new(right - x, bottom - y, 1.0))
+            });
+        }
+    }
+
+    slices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::Vector;
+
+    const BORDERS: Borders = Borders { left: 4.0, top: 2.0, right: 4.0, bottom: 6.0 };
+
+    #[test]
+    fn axis_keeps_borders() {
+        assert_eq!(axis(4.0, 2.0, 30.0, 10.0), [(0.0, 4.0, 0.0, 4.0), (4.0, 28.0, 4.0, 8.0), (28.0, 30.0, 8.0, 10.0)]);
+    }
+
+    #[test]
+    fn axis_shrinks_borders() {
+        // Smaller than its borders, the sprite keeps their ratio and has no center
+        assert_eq!(axis(6.0, 2.0, 4.0, 10.0), [(0.0, 3.0, 0.0, 6.0), (3.0, 3.0, 6.0, 8.0), (3.0, 4.0, 8.0, 10.0)]);
+    }
+
+    #[test]
+    fn nine_quads() {
+        let slices = nine_slice(Mat4::IDENTITY, Rect::new(0.5, 0.0, 0.5, 1.0), Vec2::new(16.0, 16.0), Vec2::new(40.0, 20.0), &BORDERS);
+
+        assert_eq!(slices.len(), 9);
+        // Top left corner keeps its size, the center is stretched
+        assert_eq!(slices[0].transform.transform_point3(Vec3::new(1.0, 1.0, 0.0)), Vec3::new(4.0, 2.0, 0.0));
+        assert_eq!(slices[0].uv, Rect::new(0.5, 0.0, 0.125, 0.125));
+        assert_eq!(slices[4].transform.transform_point3(Vec3::ZERO), Vec3::new(4.0, 2.0, 0.0));
+        assert_eq!(slices[4].transform.transform_point3(Vec3::new(1.0, 1.0, 0.0)), Vec3::new(36.0, 14.0, 0.0));
+        assert_eq!(slices[4].uv, Rect::new(0.625, 0.125, 0.25, 0.5));
+    }
+
+    #[test]
+    fn empty_center_is_skipped() {
+        let slices = nine_slice(Mat4::IDENTITY, Rect::new(0.0, 0.0, 1.0, 1.0), Vec2::new(16.0, 16.0), Vec2::new(8.0, 8.0), &BORDERS);
+
+        // The borders fill the sprite, only the corners are left
+        assert_eq!(slices.len(), 4);
+    }
+
+    #[test]
+    fn origin_ignores_scale() {
+        let transform = Transform { position: Vector { x: 10.0, y: 20.0 }, rotation: 0.0, scale: Vector { x: -2.0, y: 3.0 } };
+        let origin = slice_origin(&transform, Vec2::new(0.5, 0.5), Vec2::new(40.0, 20.0));
+
+        // Only the sign of the scale is kept, the size already include it
+        assert_eq!(origin.transform_point3(Vec3::ZERO), Vec3::new(30.0, 10.0, 0.0));
+        assert_eq!(origin.transform_point3(Vec3::new(40.0, 20.0, 0.0)), Vec3::new(-10.0, 30.0, 0.0));
+    }
+}
@@ -0,0 +1,200 @@
+use std::error::Error;
+
+use ggez::{glam::Vec2, graphics};
+
+use mlua::UserData;
+
+use crate::{assets::AssetsLoader, components::{Color, ParticleEmitter, Transform, Vector}, gameobject::Gameobject, lua::Script};
+
+/// Default settings of the emitters, `ParticleEmitter:new`, nil fields and particles files all start from them
+impl Default for ParticleEmitter {
+    fn default() -> Self {
+        Self {
+            emitting: true,
+            file: None,
+            rate: 10.0,
+            burst: 0,
+            lifetime: Vector { x: 1.0, y: 1.0 },
+            speed: Vector { x: 50.0, y: 50.0 },
+            direction: 0.0,
+            spread: std::f32::consts::TAU,
+            gravity: Vector { x: 0.0, y: 0.0 },
+            colors: vec![Color { r: 255.0, g: 255.0, b: 255.0, a: 255.0 }],
+            sizes: vec![8.0],
+            texture: None,
+            local_space: false,
+            max_particles: 1000
+        }
+    }
+}
+
+impl ParticleEmitter {
+    /// Settings of a particles file, the fields missing from the file keep their default value
+    pub fn from_json(file: &[u8]) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_slice(file)?)
+    }
+}
+
+struct Particle {
+    /// In world coordinates, or relative to the gameobject for local emitters
+    position: Vec2,
+    velocity: Vec2,
+    age: f32,
+    lifetime: f32
+}
+
+/// Particle drawn by the renderer, in world coordinates
+pub struct ParticleDraw {
+    pub position: Vec2,
+    pub size: f32,
+    pub color: graphics::Color
+}
+
+/// Particles alive of a gameobject, simulated in rust
+pub struct Particles {
+    particles: Vec<Particle>,
+    /// Part of a particle not emitted yet, so low rates still emit
+    pending: f32,
+    rng: fastrand::Rng
+}
+
+/// Value between a and b, t goes from 0 to 1
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Position of `t` in a curve of `count` evenly spaced values, the index of the value before it and the blend with the next one
+fn curve_position(count: usize, t: f32) -> (usize, usize, f32) {
+    let position = t.clamp(0.0, 1.0) * (count - 1) as f32;
+    let index = (position.floor() as usize).min(count - 1);
+
+    (index, (index + 1).min(count - 1), position - index as f32)
+}
+
+fn sample_size(sizes: &[f32], t: f32) -> f32 {
+    if sizes.is_empty() {
+        return 0.0;
+    }
+
+    let (a, b, blend) = curve_position(sizes.len(), t);
+    lerp(sizes[a], sizes[b], blend)
+}
+
+fn sample_color(colors: &[Color], t: f32) -> graphics::Color {
+    if colors.is_empty() {
+        return graphics::Color::WHITE;
+    }
+
+    let (a, b, blend) = curve_position(colors.len(), t);
+    let (a, b) = (colors[a].to(), colors[b].to());
+
+    graphics::Color::new(lerp(a.r, b.r, blend), lerp(a.g, b.g, blend), lerp(a.b, b.b, blend), lerp(a.a, b.a, blend))
+}
+
+impl Particles {
+    pub fn new() -> Self {
+        Self { particles: Vec::new(), pending: 0.0, rng: fastrand::Rng::new() }
+    }
+
+    fn emit(&mut self, settings: &ParticleEmitter, origin: Vec2, count: usize) {
+        let count = count.min(settings.max_particles.saturating_sub(self.particles.len()));
+
+        for _ in 0..count {
+            let angle = settings.direction + (self.rng.f32() - 0.5) * settings.spread;
+            let speed = lerp(settings.speed.x, settings.speed.y, self.rng.f32());
+
+            self.particles.push(Particle {
+                position: if settings.local_space { Vec2::ZERO } else { origin },
+                velocity: Vec2::from_angle(angle) * speed,
+                age: 0.0,
+                lifetime: lerp(settings.lifetime.x, settings.lifetime.y, self.rng.f32())
+            });
+        }
+    }
+
+    /// Move the particles, remove the dead ones and emit the new ones at `origin`
+    pub fn update(&mut self, settings: &ParticleEmitter, origin: Vec2, emitting: bool, burst: u32, delta: f32) {
+        let gravity = settings.gravity.to();
+
+        for particle in self.particles.iter_mut() {
+            particle.age += delta;
+            particle.velocity += gravity * delta;
+            particle.position += particle.velocity * delta;
+        }
+
+        self.particles.retain(|particle| particle.age < particle.lifetime);
+
+        if emitting {
+            self.pending += settings.rate.max(0.0) * delta;
+        } else {
+            self.pending = 0.0;
+        }
+
+        let count = self.pending.floor();
+        self.pending -= count;
+
+        self.emit(settings, origin, count as usize + burst as usize);
+    }
+
+    /// Particles to draw, local particles are moved to `origin`
+    pub fn draws(&self, settings: &ParticleEmitter, origin: Vec2) -> Vec<ParticleDraw> {
+        let offset = if settings.local_space { origin } else { Vec2::ZERO };
+
+        self.particles.iter().map(|particle| {
+            let t = particle.age / particle.lifetime.max(f32::EPSILON);
+
+            ParticleDraw {
+                position: particle.position + offset,
+                size: sample_size(&settings.sizes, t),
+                color: sample_color(&settings.colors, t)
+            }
+        }).collect()
+    }
+}
+
+/// Settings of an emitter, from its particles file when it has one (none while the file is loading)
+pub fn emitter_settings<'a>(emitter: &'a ParticleEmitter, asset_loader: &'a AssetsLoader) -> Option<&'a ParticleEmitter> {
+    match &emitter.file {
+        Some(file) => asset_loader.get_particles(file),
+        None => Some(emitter)
+    }
+}
+
+/// System simulating the particles of the `ParticleEmitter` component
+pub fn update_particles(go: &Gameobject, asset_loader: &AssetsLoader, delta: f32) -> mlua::Result<()> {
+    let emitter = match go.get_component::<ParticleEmitter>()? {
+        Some(emitter) => emitter,
+        None => return Ok(())
+    };
+
+    let origin = go.get_component::<Transform>()?.map(|transform| transform.position.to()).unwrap_or(Vec2::ZERO);
+
+    // The burst waits for the particles file to be loaded
+    let settings = match emitter_settings(&emitter, asset_loader) {
+        Some(settings) => settings,
+        None => return Ok(())
+    };
+
+    go.get_particles().borrow_mut().update(settings, origin, emitter.emitting, emitter.burst, delta);
+
+    if emitter.burst > 0 {
+        go.set_component_field::<ParticleEmitter, _>("burst", 0)?;
+    }
+
+    Ok(())
+}
+
+/// Default settings of the emitters, available as `rust_particles` in lua
+struct LuaParticles;
+
+impl UserData for LuaParticles {
+    fn add_methods<'lua, M: mlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("default", |_, _, ()| {
+            Ok(ParticleEmitter::default())
+        });
+    }
+}
+
+pub fn add_particles_library(script: &Script) {
+    script.get_state().globals().set("rust_particles", LuaParticles).unwrap();
+}
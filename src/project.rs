@@ -0,0 +1,83 @@
+use std::error::Error;
+
+use serde::Deserialize;
+
+pub const PROJECT_CONFIG_FILE: &str = "pebble.json";
+
+/// Layer used by the gameobjects without a `Layer` component
+pub const DEFAULT_LAYER: &str = "default";
+
+/// Render layer, layers are drawn in the order of the config
+#[derive(Clone, Debug, Deserialize)]
+pub struct LayerConfig {
+    pub name: String,
+    /// Draw the gameobjects with the smallest y first, for top down games
+    #[serde(default)]
+    pub y_sort: bool,
+    /// Affected by the lights of the scene, HUD layers usually are not
+    #[serde(default = "default_lit")]
+    pub lit: bool
+}
+
+fn default_lit() -> bool {
+    true
+}
+
+/// Size the scene is drawn at before being scaled to the window, for pixel art games
+#[derive(Clone, Debug, Deserialize)]
+pub struct ResolutionConfig {
+    pub width: u32,
+    pub height: u32
+}
+
+/// Project settings read from `pebble.json`, next to `main.lua`
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProjectConfig {
+    #[serde(default)]
+    pub layers: Vec<LayerConfig>,
+    /// The scene is drawn at the size of the window when none
+    #[serde(default)]
+    pub resolution: Option<ResolutionConfig>
+}
+
+impl Default for ProjectConfig {
+    fn default() -> Self {
+        Self { layers: vec![LayerConfig { name: DEFAULT_LAYER.to_string(), y_sort: false, lit: true }], resolution: None }
+    }
+}
+
+impl ProjectConfig {
+    pub fn from_json(file: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let mut config : ProjectConfig = serde_json::from_slice(file)?;
+
+        // The default layer is always available, drawn first unless the config place it
+        if !config.layers.iter().any(|layer| layer.name == DEFAULT_LAYER) {
+            config.layers.insert(0, LayerConfig { name: DEFAULT_LAYER.to_string(), y_sort: false, lit: true });
+        }
+
+        if let Some(resolution) = &config.resolution {
+            if resolution.width == 0 || resolution.height == 0 {
+                return Err("the resolution cannot be 0".into());
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Read the project config, the default config is used when the file does not exist or is invalid
+    pub fn load(filename: &str) -> Self {
+        let config = match std::fs::read(filename) {
+            Ok(file) => Self::from_json(&file),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into())
+        };
+
+        match config {
+            Ok(config) => config,
+            Err(err) => {
+                error!("Cannot read project config {} : {}", filename, err);
+                Self::default()
+            }
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use ggez::graphics::Rect;
+
+use crate::components::RenderTarget;
+
+pub fn validate_size(size: &u32) -> Result<(), String> {
+    match size {
+        0 => Err(String::from("the size of a render target cannot be 0")),
+        _ => Ok(())
+    }
+}
+
+impl RenderTarget {
+    /// Area drawn by the cameras of the target, in pixels
+    pub fn screen(&self) -> Rect {
+        Rect::new(0.0, 0.0, self.width as f32, self.height as f32)
+    }
+}
@@ -0,0 +1,385 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use ggez::{graphics::{self, BlendMode, Canvas, Color, DrawParam, Image, InstanceArray, Mesh, Rect, Sampler, Shader, ShaderParams, ShaderParamsBuilder, Text}, glam::{Mat4, Vec2, Vec3}, Context};
+
+use crate::{assets::AssetsLoader, components::Layer, lighting::{flat_normal_map, lighting_shader, LightDraw, LightUniforms, Lighting, MAX_LIGHTS}, project::{LayerConfig, DEFAULT_LAYER}, shader::{RawUniforms, MAX_UNIFORMS}};
+
+/// Quad drawn with the cached unit quad, transform include the size of the quad in pixels
+pub struct SpriteDraw {
+    pub texture: Option<String>,
+    /// Part of the texture, in uv coordinates
+    pub uv: Rect,
+    pub transform: Mat4,
+    pub color: Color,
+    pub shader: Option<ShaderDraw>,
+    pub blend: BlendMode,
+    /// Texture of the normals, with the same uv as the texture
+    pub normal_map: Option<String>
+}
+
+/// Already batched geometry (tilemap layers, shapes, ...), drawn in a single call
+pub struct MeshDraw {
+    pub mesh: Mesh,
+    pub texture: Option<String>,
+    /// Texture of the normals, with the same uv as the texture
+    pub normal_map: Option<String>,
+    pub shader: Option<ShaderDraw>,
+    pub blend: BlendMode,
+    pub param: DrawParam
+}
+
+/// Custom shader of a draw and the values of its uniforms
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderDraw {
+    pub name: String,
+    pub uniforms: [f32; MAX_UNIFORMS]
+}
+
+/// What the sprites drawn with a single instanced draw call share
+#[derive(Clone, PartialEq)]
+struct BatchKey {
+    texture: Option<String>,
+    shader: Option<ShaderDraw>,
+    blend: BlendMode,
+    /// Drawn with the lighting shader and this normal map
+    lighting: Option<Option<String>>
+}
+
+type Batch = (BatchKey, Vec<DrawParam>);
+
+/// Blend mode of a material, by name
+pub fn blend_mode(name: &str) -> Result<BlendMode, String> {
+    match name {
+        "alpha" => Ok(BlendMode::ALPHA),
+        "additive" => Ok(BlendMode::ADD),
+        "multiply" => Ok(BlendMode::MULTIPLY),
+        "replace" => Ok(BlendMode::REPLACE),
+        "premultiplied" => Ok(BlendMode::PREMULTIPLIED),
+        other => Err(format!("{} is not a blend mode, expected alpha, additive, multiply, replace or premultiplied", other))
+    }
+}
+
+pub fn validate_blend(name: &str) -> Result<(), String> {
+    blend_mode(name).map(|_| ())
+}
+
+/// Color multiplying the texture, textures drawn with premultiplied alpha need a premultiplied color too
+fn blend_color(color: Color, blend: BlendMode) -> Color {
+    if blend == BlendMode::PREMULTIPLIED {
+        Color::new(color.r * color.a, color.g * color.a, color.b * color.a, color.a)
+    } else {
+        color
+    }
+}
+
+/// Where an item is drawn, items are sorted by layer, then z index, then y for y sorted layers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderOrder {
+    layer: usize,
+    z_index: f32,
+    y: f32
+}
+
+impl RenderOrder {
+    fn compare(&self, other: &Self) -> Ordering {
+        self.layer.cmp(&other.layer)
+            .then(self.z_index.total_cmp(&other.z_index))
+            .then(self.y.total_cmp(&other.y))
+    }
+}
+
+enum RenderItem {
+    Sprite(SpriteDraw),
+    Mesh(MeshDraw),
+    Text { text: Text, param: DrawParam }
+}
+
+/// Collect everything drawn during a frame and draw quads sharing a texture with a single instanced draw call
+pub struct Renderer {
+    quad: Mesh,
+    layers: Vec<LayerConfig>,
+    items: Vec<(RenderOrder, RenderItem)>,
+    /// Instance arrays reused between frames, by texture
+    pools: HashMap<Option<String>, Vec<InstanceArray>>,
+    /// Instance arrays of each pool already drawn during this frame
+    used: HashMap<Option<String>, usize>,
+    /// Uniforms buffers of the shaders, by shader
+    shader_params: HashMap<String, ShaderParams<RawUniforms>>,
+    lighting: Lighting,
+    lighting_shader: Shader,
+    /// Normal map of the sprites without one
+    flat_normal_map: Image,
+    /// Uniforms buffers of the lighting shader, by normal map
+    light_params: HashMap<Option<String>, ShaderParams<LightUniforms>>,
+    /// The lights beyond `MAX_LIGHTS` are only reported once
+    lights_dropped: bool,
+    assets_generation: u64
+}
+
+impl Renderer {
+    pub fn new(ctx: &ggez::Context, layers: Vec<LayerConfig>) -> Self {
+        Self {
+            quad: quad(ctx, Rect::new(0.0, 0.0, 1.0, 1.0), Rect::new(0.0, 0.0, 1.0, 1.0)),
+            layers,
+            items: Vec::new(),
+            pools: HashMap::new(),
+            used: HashMap::new(),
+            shader_params: HashMap::new(),
+            lighting: Lighting::default(),
+            lighting_shader: lighting_shader(ctx).expect("The lighting shader is valid"),
+            flat_normal_map: flat_normal_map(ctx),
+            light_params: HashMap::new(),
+            lights_dropped: false,
+            assets_generation: 0
+        }
+    }
+
+    /// Order of a gameobject, `y` is its position and only used when its layer is y sorted
+    pub fn order(&self, layer: Option<&Layer>, y: f32) -> mlua::Result<RenderOrder> {
+        let name = layer.map(|layer| layer.name.as_str()).unwrap_or(DEFAULT_LAYER);
+
+        let index = self.layers.iter().position(|config| config.name == name)
+            .ok_or_else(|| mlua::Error::RuntimeError(format!("Unknown layer {}, layers are declared in pebble.json", name)))?;
+
+        Ok(RenderOrder {
+            layer: index,
+            z_index: layer.map(|layer| layer.z_index).unwrap_or(0.0),
+            y: if self.layers[index].y_sort { y } else { 0.0 }
+        })
+    }
+
+    pub fn push_sprite(&mut self, order: RenderOrder, sprite: SpriteDraw) {
+        self.items.push((order, RenderItem::Sprite(sprite)));
+    }
+
+    pub fn push_mesh(&mut self, order: RenderOrder, mesh: MeshDraw) {
+        self.items.push((order, RenderItem::Mesh(mesh)));
+    }
+
+    pub fn push_text(&mut self, order: RenderOrder, text: Text, param: DrawParam) {
+        self.items.push((order, RenderItem::Text { text, param }));
+    }
+
+    pub fn push_light(&mut self, light: LightDraw) {
+        self.lighting.lights.push(light);
+    }
+
+    pub fn push_ambient_light(&mut self, color: Color) {
+        self.lighting.add_ambient(color);
+    }
+
+    /// Draw the items pushed since the last clear, only the items of the given layers when some are given.
+    /// Items are sorted by their order (ties keep the order they were pushed),
+    /// consecutive sprites sharing a texture, a shader and a blend mode are drawn with a single draw call, whatever their order.
+    /// When drawing into a render target, the items using its texture are skipped as it cannot be read while written.
+    /// Once the scene has a light, the items of lit layers without a custom shader are drawn with the lighting shader,
+    /// `view` is the transform from the world to the pixels of the canvas.
+    pub fn draw(&mut self, ctx: &mut Context, canvas: &mut Canvas, assets: &AssetsLoader, view: Mat4, layers: Option<&[String]>, target: Option<&str>) {
+        // Instance arrays and shader params keep the image they were created with, drop them when a texture is reloaded
+        if self.assets_generation != assets.get_generation() {
+            self.pools.clear();
+            self.used.clear();
+            self.light_params.clear();
+            self.assets_generation = assets.get_generation();
+        }
+
+        if self.lighting.lights.len() > MAX_LIGHTS && !self.lights_dropped {
+            warn!("{} lights in the scene, only the first {} are drawn", self.lighting.lights.len(), MAX_LIGHTS);
+            self.lights_dropped = true;
+        }
+
+        let lights = self.lighting.uniforms(view);
+
+        let mut batches : Vec<Batch> = Vec::new();
+
+        let mut items = std::mem::take(&mut self.items);
+        items.sort_by(|(a, _), (b, _)| a.compare(b));
+
+        for (order, item) in items.iter() {
+            if let Some(layers) = layers {
+                if !layers.contains(&self.layers[order.layer].name) {
+                    continue;
+                }
+            }
+
+            let texture = match item {
+                RenderItem::Sprite(sprite) => sprite.texture.as_deref(),
+                RenderItem::Mesh(mesh) => mesh.texture.as_deref(),
+                RenderItem::Text { .. } => None
+            };
+
+            if target.is_some() && texture == target {
+                continue;
+            }
+
+            let lit = self.lighting.is_active() && self.layers[order.layer].lit;
+
+            match item {
+                RenderItem::Sprite(sprite) => {
+                    let param = DrawParam::default()
+                        .src(Rect::new(sprite.uv.x, sprite.uv.y, sprite.uv.w, sprite.uv.h))
+                        .transform(sprite.transform)
+                        .color(blend_color(sprite.color, sprite.blend));
+
+                    let key = BatchKey {
+                        texture: sprite.texture.clone(),
+                        shader: sprite.shader.clone(),
+                        blend: sprite.blend,
+                        lighting: (lit && sprite.shader.is_none()).then(|| sprite.normal_map.clone())
+                    };
+
+                    // Only the last batch is extended, so overlapping sprites keep the order they were pushed
+                    match batches.last_mut() {
+                        Some((batch, params)) if *batch == key => params.push(param),
+                        _ => batches.push((key, vec![param]))
+                    }
+                },
+                RenderItem::Mesh(MeshDraw { mesh, texture, normal_map, shader, blend, param }) => {
+                    self.draw_batches(ctx, canvas, assets, &lights, std::mem::take(&mut batches));
+                    if lit && shader.is_none() {
+                        self.set_lighting(ctx, canvas, assets, &lights, normal_map.as_deref());
+                    } else {
+                        self.set_shader(ctx, canvas, assets, shader.as_ref());
+                    }
+
+                    canvas.set_blend_mode(*blend);
+
+                    let param = param.color(blend_color(param.color, *blend));
+
+                    match texture {
+                        Some(texture) => if let Some(image) = assets.get_texture(texture) {
+                            canvas.draw_textured_mesh(mesh.clone(), image.clone(), param);
+                        },
+                        None => canvas.draw(mesh, param)
+                    }
+
+                    canvas.set_default_shader();
+                    canvas.set_blend_mode(BlendMode::ALPHA);
+                },
+                RenderItem::Text { text, param } => {
+                    self.draw_batches(ctx, canvas, assets, &lights, std::mem::take(&mut batches));
+                    canvas.draw(text, *param);
+                }
+            }
+        }
+
+        self.draw_batches(ctx, canvas, assets, &lights, batches);
+        self.items = items;
+    }
+
+    /// Drop the items of the frame, once every camera drew them
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.used.clear();
+        self.lighting.clear();
+    }
+
+    /// Use the shader of a draw, the default shader when there is none or while it is loading
+    fn set_shader(&mut self, ctx: &mut Context, canvas: &mut Canvas, assets: &AssetsLoader, shader: Option<&ShaderDraw>) {
+        let (shader, asset) = match shader.and_then(|shader| assets.get_shader(&shader.name).map(|asset| (shader, asset))) {
+            Some(shader) => shader,
+            None => {
+                canvas.set_default_shader();
+                return;
+            }
+        };
+
+        let uniforms = RawUniforms::new(&shader.uniforms);
+
+        // Uniforms set during a frame get their own slot of the buffer, so the params are shared by every draw of the shader
+        let params = self.shader_params.entry(shader.name.clone()).or_insert_with(|| ShaderParamsBuilder::new(&uniforms).build(ctx));
+        params.set_uniforms(ctx, &uniforms);
+
+        canvas.set_shader(asset.get_shader());
+        canvas.set_shader_params(params);
+    }
+
+    /// Use the lighting shader with a normal map, the flat one when there is none or while it is loading
+    fn set_lighting(&mut self, ctx: &mut Context, canvas: &mut Canvas, assets: &AssetsLoader, lights: &LightUniforms, normal_map: Option<&str>) {
+        let image = normal_map.and_then(|name| assets.get_texture(name));
+        let key = image.and(normal_map).map(|name| name.to_string());
+        let image = image.unwrap_or(&self.flat_normal_map);
+
+        let params = self.light_params.entry(key)
+            .or_insert_with(|| ShaderParamsBuilder::new(lights).images(&[image], &[Sampler::default()], false).build(ctx));
+        params.set_uniforms(ctx, lights);
+
+        canvas.set_shader(&self.lighting_shader);
+        canvas.set_shader_params(params);
+    }
+
+    fn draw_batches(&mut self, ctx: &mut Context, canvas: &mut Canvas, assets: &AssetsLoader, lights: &LightUniforms, batches: Vec<Batch>) {
+        for (BatchKey { texture, shader, blend, lighting }, params) in batches {
+            let image = match &texture {
+                Some(name) => match assets.get_texture(name) {
+                    Some(image) => Some(image.clone()),
+                    // The texture can be missing for a frame, while the assets are loading
+                    None => continue
+                },
+                None => None
+            };
+
+            match &lighting {
+                Some(normal_map) => self.set_lighting(ctx, canvas, assets, lights, normal_map.as_deref()),
+                None => self.set_shader(ctx, canvas, assets, shader.as_ref())
+            }
+
+            canvas.set_blend_mode(blend);
+
+            // An instance array can only be drawn once per frame, the canvas read it when it is finished
+            let index = self.used.entry(texture.clone()).or_insert(0);
+            let pool = self.pools.entry(texture).or_default();
+
+            if *index >= pool.len() {
+                pool.push(InstanceArray::new(&*ctx, image));
+            }
+
+            let instances = &mut pool[*index];
+            *index += 1;
+
+            instances.set(params);
+            canvas.draw_instanced_mesh(self.quad.clone(), instances, DrawParam::default());
+            canvas.set_default_shader();
+            canvas.set_blend_mode(BlendMode::ALPHA);
+        }
+    }
+}
+
+/// Transform of a quad of `size` pixels, `pivot` is the normalized origin of the quad
+pub fn sprite_transform(position: Vec2, rotation: f32, scale: Vec2, pivot: Vec2, size: Vec2) -> Mat4 {
+    Mat4::from_translation(Vec3::new(position.x, position.y, 0.0))
+        * Mat4::from_rotation_z(rotation)
+        * Mat4::from_scale(Vec3::new(scale.x, scale.y, 1.0))
+        * Mat4::from_translation(Vec3::new(-pivot.x * size.x, -pivot.y * size.y, 0.0))
+        * Mat4::from_scale(Vec3::new(size.x, size.y, 1.0))
+}
+
+/// Quad mesh, uv is the part of the texture mapped onto it
+pub fn quad(ctx: &ggez::Context, rect: Rect, uv: Rect) -> graphics::Mesh {
+    let vertices = vec![
+        graphics::Vertex {
+            position: [rect.x, rect.y],
+            uv: [uv.x, uv.y],
+            color: [1.0, 1.0, 1.0, 1.0]
+        },
+        graphics::Vertex {
+            position: [rect.x + rect.w, rect.y],
+            uv: [uv.x + uv.w, uv.y],
+            color: [1.0, 1.0, 1.0, 1.0]
+        },
+        graphics::Vertex {
+            position: [rect.x + rect.w, rect.y + rect.h],
+            uv: [uv.x + uv.w, uv.y + uv.h],
+            color: [1.0, 1.0, 1.0, 1.0]
+        },
+        graphics::Vertex {
+            position: [rect.x, rect.y + rect.h],
+            uv: [uv.x, uv.y + uv.h],
+            color: [1.0, 1.0, 1.0, 1.0]
+        }
+    ];
+
+    let indices = vec![0,1,2,2,3,0];
+
+    graphics::Mesh::from_data(ctx, graphics::MeshData { vertices: &vertices, indices: &indices })
+}
@@ -0,0 +1,66 @@
+use ggez::{glam::Vec2, graphics::Rect};
+
+use crate::project::ResolutionConfig;
+
+impl ResolutionConfig {
+    /// The virtual screen, in virtual pixels
+    pub fn screen(&self) -> Rect {
+        Rect::new(0.0, 0.0, self.width as f32, self.height as f32)
+    }
+
+    /// Largest whole scale of the virtual screen fitting in the window, at least 1
+    pub fn scale(&self, window: Rect) -> f32 {
+        (window.w / self.width as f32).min(window.h / self.height as f32).floor().max(1.0)
+    }
+
+    /// Part of the window showing the virtual screen, centered with black bars around it
+    pub fn area(&self, window: Rect) -> Rect {
+        let scale = self.scale(window);
+        let (width, height) = (self.width as f32 * scale, self.height as f32 * scale);
+
+        Rect::new(
+            window.x + ((window.w - width) / 2.0).floor(),
+            window.y + ((window.h - height) / 2.0).floor(),
+            width,
+            height
+        )
+    }
+
+    /// Convert a position on the window, like the mouse position, to virtual pixels
+    pub fn to_virtual(&self, window: Rect, point: Vec2) -> Vec2 {
+        let area = self.area(window);
+        (point - Vec2::new(area.x, area.y)) / self.scale(window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESOLUTION: ResolutionConfig = ResolutionConfig { width: 320, height: 180 };
+
+    #[test]
+    fn whole_scale() {
+        assert_eq!(RESOLUTION.scale(Rect::new(0.0, 0.0, 1280.0, 720.0)), 4.0);
+        // The smallest side decides, the scale is rounded down
+        assert_eq!(RESOLUTION.scale(Rect::new(0.0, 0.0, 1920.0, 500.0)), 2.0);
+        // A window smaller than the virtual screen still draws it at scale 1
+        assert_eq!(RESOLUTION.scale(Rect::new(0.0, 0.0, 200.0, 100.0)), 1.0);
+    }
+
+    #[test]
+    fn centered_area() {
+        assert_eq!(RESOLUTION.area(Rect::new(0.0, 0.0, 1280.0, 720.0)), Rect::new(0.0, 0.0, 1280.0, 720.0));
+        assert_eq!(RESOLUTION.area(Rect::new(0.0, 0.0, 1001.0, 600.0)), Rect::new(20.0, 30.0, 960.0, 540.0));
+    }
+
+    #[test]
+    fn window_to_virtual() {
+        let window = Rect::new(0.0, 0.0, 1001.0, 600.0);
+
+        assert_eq!(RESOLUTION.to_virtual(window, Vec2::new(20.0, 30.0)), Vec2::ZERO);
+        assert_eq!(RESOLUTION.to_virtual(window, Vec2::new(500.0, 300.0)), Vec2::new(160.0, 90.0));
+        // The black bars are outside of the virtual screen
+        assert_eq!(RESOLUTION.to_virtual(window, Vec2::new(5.0, 0.0)), Vec2::new(-5.0, -10.0));
+    }
+}
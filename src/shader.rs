@@ -0,0 +1,153 @@
+use std::{collections::HashMap, error::Error};
+
+use crevice::std140::AsStd140;
+use ggez::{graphics::{Shader, ShaderBuilder}, mint::ColumnMatrix4};
+use mlua::{FromLua, ToLua};
+use naga::{valid::{Capabilities, ValidationFlags, Validator}, AddressSpace, ScalarKind, ShaderStage, TypeInner};
+
+/// Size of the uniforms of a shader, in floats (256 bytes)
+pub const MAX_UNIFORMS: usize = 64;
+
+/// Entry point of the fragment shader, the vertex shader is the one of ggez
+const FRAGMENT_ENTRY_POINT: &str = "fs_main";
+
+/// Value of a uniform set from lua, a number for a `f32` and an array of numbers for a vector
+#[derive(Clone, Debug)]
+pub enum UniformValue {
+    Number(f32),
+    Vector(Vec<f32>)
+}
+
+impl<'lua> FromLua<'lua> for UniformValue {
+    fn from_lua(lua_value: mlua::Value<'lua>, lua: &'lua mlua::Lua) -> mlua::Result<Self> {
+        match lua_value {
+            mlua::Value::Integer(_) | mlua::Value::Number(_) => Ok(Self::Number(f32::from_lua(lua_value, lua)?)),
+            mlua::Value::Table(_) => Ok(Self::Vector(Vec::<f32>::from_lua(lua_value, lua)?)),
+            other => Err(mlua::Error::RuntimeError(format!("Cannot convert {} to a uniform, expected a number or an array of numbers", other.type_name())))
+        }
+    }
+}
+
+impl<'lua> ToLua<'lua> for UniformValue {
+    fn to_lua(self, lua: &'lua mlua::Lua) -> mlua::Result<mlua::Value<'lua>> {
+        match self {
+            Self::Number(value) => value.to_lua(lua),
+            Self::Vector(values) => values.to_lua(lua)
+        }
+    }
+}
+
+/// Uniforms as sent to the gpu, the shader reads them at `@group(3) @binding(0)`
+#[derive(AsStd140)]
+pub struct RawUniforms {
+    a: ColumnMatrix4<f32>,
+    b: ColumnMatrix4<f32>,
+    c: ColumnMatrix4<f32>,
+    d: ColumnMatrix4<f32>
+}
+
+impl RawUniforms {
+    pub fn new(values: &[f32; MAX_UNIFORMS]) -> Self {
+        let matrix = |index: usize| {
+            let mut columns = [0.0; 16];
+            columns.copy_from_slice(&values[index * 16..(index + 1) * 16]);
+            ColumnMatrix4::from(columns)
+        };
+
+        Self { a: matrix(0), b: matrix(1), c: matrix(2), d: matrix(3) }
+    }
+}
+
+/// Member of the uniform struct of a shader
+struct UniformField {
+    name: String,
+    /// In floats from the start of the struct
+    offset: usize,
+    components: usize
+}
+
+/// Fragment shader written in wgsl, its uniforms are read from the source
+pub struct ShaderAsset {
+    shader: Shader,
+    fields: Vec<UniformField>
+}
+
+impl ShaderAsset {
+    /// Compile a wgsl fragment shader, errors point to the line of the source
+    pub fn from_wgsl(ctx: &ggez::Context, filename: &str, source: &str) -> Result<Self, Box<dyn Error>> {
+        let module = naga::front::wgsl::parse_str(source).map_err(|err| err.emit_to_string_with_path(source, filename))?;
+
+        Validator::new(ValidationFlags::all(), Capabilities::empty())
+            .validate(&module)
+            .map_err(|err| err.emit_to_string_with_path(source, filename))?;
+
+        if !module.entry_points.iter().any(|entry| entry.stage == ShaderStage::Fragment && entry.name == FRAGMENT_ENTRY_POINT) {
+            return Err(format!("Shader {} has no fragment entry point, expected @fragment fn {}", filename, FRAGMENT_ENTRY_POINT).into());
+        }
+
+        let fields = uniform_fields(&module).map_err(|err| format!("Shader {} : {}", filename, err))?;
+        let shader = ShaderBuilder::new().fragment_code(source).build(ctx)?;
+
+        Ok(Self { shader, fields })
+    }
+
+    pub fn get_shader(&self) -> &Shader {
+        &self.shader
+    }
+
+    /// Uniforms of the shader from their lua values, uniforms not given are zero
+    pub fn uniforms(&self, values: &HashMap<String, UniformValue>) -> Result<[f32; MAX_UNIFORMS], String> {
+        let mut uniforms = [0.0; MAX_UNIFORMS];
+
+        for (name, value) in values.iter() {
+            let field = self.fields.iter().find(|field| field.name == *name)
+                .ok_or_else(|| format!("Unknown uniform {}", name))?;
+
+            let components = match value {
+                UniformValue::Number(value) => vec![*value],
+                UniformValue::Vector(values) => values.clone()
+            };
+
+            if components.len() != field.components {
+                return Err(format!("Uniform {} expects {} numbers, got {}", name, field.components, components.len()));
+            }
+
+            uniforms[field.offset..field.offset + field.components].copy_from_slice(&components);
+        }
+
+        Ok(uniforms)
+    }
+}
+
+/// Members of the struct bound at `@group(3) @binding(0)`, only `f32` and vectors of `f32` are supported
+fn uniform_fields(module: &naga::Module) -> Result<Vec<UniformField>, String> {
+    let global = module.global_variables.iter()
+        .map(|(_, global)| global)
+        .find(|global| global.space == AddressSpace::Uniform && global.binding.as_ref().is_some_and(|binding| binding.group == 3 && binding.binding == 0));
+
+    let global = match global {
+        Some(global) => global,
+        None => return Ok(Vec::new())
+    };
+
+    let (members, span) = match &module.types[global.ty].inner {
+        TypeInner::Struct { members, span } => (members, *span),
+        _ => return Err(String::from("uniforms at @group(3) @binding(0) must be a struct"))
+    };
+
+    if span as usize > MAX_UNIFORMS * 4 {
+        return Err(format!("uniforms take {} bytes, at most {} are supported", span, MAX_UNIFORMS * 4));
+    }
+
+    members.iter().map(|member| {
+        let name = member.name.clone().unwrap_or_default();
+
+        let components = match module.types[member.ty].inner {
+            TypeInner::Scalar { kind: ScalarKind::Float, width: 4 } => 1,
+            TypeInner::Vector { size, kind: ScalarKind::Float, width: 4 } => size as usize,
+            _ => return Err(format!("uniform {} is not supported, only f32, vec2<f32>, vec3<f32> and vec4<f32> are", name))
+        };
+
+        Ok(UniformField { name, offset: member.offset as usize / 4, components })
+    }).collect()
+}
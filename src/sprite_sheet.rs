@@ -0,0 +1,141 @@
+use std::error::Error;
+
+use ggez::{glam::Vec2, graphics::Rect};
+use serde::Deserialize;
+
+use crate::assets::resolve_path;
+
+/// Slice the image in frames of the same size, frames are read from left to right then top to bottom
+#[derive(Deserialize)]
+struct GridDescriptor {
+    width: u32,
+    height: u32,
+    #[serde(default)]
+    margin: u32,
+    #[serde(default)]
+    spacing: u32,
+    /// Name of each frame, the index of the frame is used when there is no name
+    #[serde(default)]
+    names: Vec<String>
+}
+
+#[derive(Deserialize)]
+struct FrameDescriptor {
+    name: String,
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32
+}
+
+/// Sprite sheet file, a json file next to the image
+#[derive(Deserialize)]
+struct SheetDescriptor {
+    /// Relative to the json file
+    image: String,
+    grid: Option<GridDescriptor>,
+    #[serde(default)]
+    frames: Vec<FrameDescriptor>
+}
+
+/// Transparent borders removed from a frame when the sheet was exported
+#[derive(Clone, Copy, Debug)]
+pub struct FrameTrim {
+    /// Position of the region in the untrimmed frame
+    pub offset: Vec2,
+    /// Size of the untrimmed frame
+    pub source_size: Vec2
+}
+
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub name: String,
+    pub region: Rect,
+    pub trim: Option<FrameTrim>
+}
+
+impl Frame {
+    /// Pivot of the sprite relative to the region, a pivot set on a trimmed frame is relative to the untrimmed frame so it does not move between frames
+    pub fn pivot(&self, pivot: Vec2, flip_x: bool, flip_y: bool) -> Vec2 {
+        let trim = match &self.trim {
+            Some(trim) => trim,
+            None => return pivot
+        };
+
+        let size = Vec2::new(self.region.w, self.region.h);
+        let mut offset = trim.offset;
+
+        // The region is mirrored inside the untrimmed frame
+        if flip_x {
+            offset.x = trim.source_size.x - offset.x - size.x;
+        }
+
+        if flip_y {
+            offset.y = trim.source_size.y - offset.y - size.y;
+        }
+
+        (pivot * trim.source_size - offset) / size
+    }
+}
+
+/// Image sliced in named frames
+pub struct SpriteSheet {
+    image: String,
+    frames: Vec<Frame>
+}
+
+impl SpriteSheet {
+    pub fn new(image: &str, frames: Vec<Frame>) -> Self {
+        Self { image: image.to_string(), frames }
+    }
+
+    /// Return the name of the image described by the sprite sheet file `filename`, it need to be loaded before slicing the sheet
+    pub fn read_image(filename: &str, file: &[u8]) -> Result<String, Box<dyn Error>> {
+        Ok(resolve_path(filename, &serde_json::from_slice::<SheetDescriptor>(file)?.image))
+    }
+
+    pub fn from_json(filename: &str, file: &[u8], image_width: u32, image_height: u32) -> Result<Self, Box<dyn Error>> {
+        let descriptor : SheetDescriptor = serde_json::from_slice(file)?;
+        let mut frames = Vec::new();
+
+        if let Some(grid) = descriptor.grid {
+            if grid.width == 0 || grid.height == 0 {
+                return Err("Sprite sheet grid width and height need to be greater than 0".into());
+            }
+
+            let mut y = grid.margin;
+
+            while y + grid.height <= image_height {
+                let mut x = grid.margin;
+
+                while x + grid.width <= image_width {
+                    let index = frames.len();
+
+                    frames.push(Frame {
+                        name: grid.names.get(index).cloned().unwrap_or_else(|| index.to_string()),
+                        region: Rect::new(x as f32, y as f32, grid.width as f32, grid.height as f32),
+                        trim: None
+                    });
+
+                    x += grid.width + grid.spacing;
+                }
+
+                y += grid.height + grid.spacing;
+            }
+        }
+
+        for frame in descriptor.frames {
+            frames.push(Frame { name: frame.name, region: Rect::new(frame.x, frame.y, frame.w, frame.h), trim: None });
+        }
+
+        Ok(Self::new(&resolve_path(filename, &descriptor.image), frames))
+    }
+
+    pub fn get_image(&self) -> &String {
+        &self.image
+    }
+
+    pub fn get_frame(&self, name: &str) -> Option<&Frame> {
+        self.frames.iter().find(|frame| frame.name == name)
+    }
+}
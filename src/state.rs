@@ -1,15 +1,19 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc, Timelike};
 use ggegui::{Gui, egui};
-use ggez::{Context, event::EventHandler, graphics::{self, DrawParam, Color}, glam::Vec2};
-use mlua::Table;
+use ggez::{Context, event::EventHandler, graphics::{self, DrawParam, Color, Rect, Sampler}, input::{keyboard::{KeyCode, KeyInput}, mouse::MouseButton}, glam::{Mat4, Vec2, Vec3}};
 
-use crate::{assets::{AssetsLoader, LuaAsset}, core::Core, LOG};
+use crate::{assets::AssetsLoader, capture::{RECORDING_KEY, SCREENSHOT_KEY}, debug::OVERLAY_KEY, components::{Camera, RenderTarget, Mouse, Vector}, core::Core, gameobject::Gameobject, renderer::Renderer, LOG};
 
 
 /// The state of the engine. Handle gameobject, assets loading, rendering, gameloop, gui, etc...
 pub struct State {
     assets_loader: AssetsLoader,
     core: Core,
+    renderer: Renderer,
+    /// Image the scene is drawn into when the project has a virtual resolution
+    virtual_screen: Option<graphics::Image>,
     gui: Gui
 }
 
@@ -17,6 +21,9 @@ impl State {
     pub fn new(core: Core, ctx: &Context) -> Self {
         Self {
             assets_loader: AssetsLoader::new(),
+            renderer: Renderer::new(ctx, core.config.layers.clone()),
+            virtual_screen: core.config.resolution.as_ref()
+                .map(|resolution| graphics::Image::new_canvas_image(ctx, ctx.gfx.surface_format(), resolution.width, resolution.height, 1)),
             core,
             gui: Gui::new(ctx)
         }
@@ -33,6 +40,123 @@ impl State {
             go.init();
         }
     }
+
+    /// Size of the window in pixels
+    fn window(ctx: &Context) -> Rect {
+        let (width, height) = ctx.gfx.drawable_size();
+        Rect::new(0.0, 0.0, width, height)
+    }
+
+    /// Size the scene is drawn at, the virtual resolution or the window
+    fn screen(&self, ctx: &Context) -> Rect {
+        match &self.core.config.resolution {
+            Some(resolution) => resolution.screen(),
+            None => State::window(ctx)
+        }
+    }
+
+    fn mouse(&self, ctx: &Context) -> Mouse {
+        let position = Vec2::from(ctx.mouse.position());
+
+        let position = match &self.core.config.resolution {
+            Some(resolution) => resolution.to_virtual(State::window(ctx), position),
+            None => position
+        };
+
+        Mouse {
+            position: Vector { x: position.x, y: position.y },
+            left: ctx.mouse.button_pressed(MouseButton::Left),
+            right: ctx.mouse.button_pressed(MouseButton::Right),
+            middle: ctx.mouse.button_pressed(MouseButton::Middle)
+        }
+    }
+
+    /// Draw the scene in a canvas of `screen` pixels through each camera, or once with `layers` without camera.
+    fn draw_view(&mut self, ctx: &mut Context, canvas: &mut graphics::Canvas, cameras: &[&Camera], screen: Rect, layers: Option<&[String]>, target: Option<&str>) -> Result<(), ggez::GameError> {
+        if cameras.is_empty() {
+            self.renderer.draw(ctx, canvas, &self.assets_loader, Mat4::IDENTITY, layers, target);
+        }
+
+        let projection = Mat4::from(canvas.projection());
+
+        for camera in cameras.iter() {
+            let viewport = camera.viewport(screen);
+
+            // Nothing would be visible, and the scissor rect cannot be empty
+            if viewport.w < 1.0 || viewport.h < 1.0 {
+                continue;
+            }
+
+            canvas.set_scissor_rect(viewport)?;
+            let view = camera.view(screen);
+            canvas.set_projection(projection * view);
+
+            self.renderer.draw(ctx, canvas, &self.assets_loader, view, camera.layers.as_deref(), target);
+        }
+
+        Ok(())
+    }
+
+    /// Draw the debug shapes on the window through the same cameras as the scene, after the virtual screen is scaled up.
+    /// `upscale` is the transform from the pixels of the screen to the pixels of the window.
+    fn draw_debug(&self, ctx: &mut Context, canvas: &mut graphics::Canvas, cameras: &[&Camera], screen: Rect, upscale: Mat4) -> Result<(), ggez::GameError> {
+        let debug = self.core.debug.borrow();
+
+        if cameras.is_empty() {
+            debug.draw(ctx, canvas, upscale);
+        }
+
+        let scale = upscale.transform_vector3(Vec3::X).length();
+
+        for camera in cameras.iter() {
+            let viewport = camera.viewport(screen);
+
+            if viewport.w < 1.0 || viewport.h < 1.0 {
+                continue;
+            }
+
+            let corner = upscale.transform_point3(Vec3::new(viewport.x, viewport.y, 0.0));
+            canvas.set_scissor_rect(Rect::new(corner.x, corner.y, viewport.w * scale, viewport.h * scale))?;
+
+            debug.draw(ctx, canvas, upscale * camera.view(screen));
+        }
+
+        canvas.set_default_scissor_rect();
+        Ok(())
+    }
+}
+
+type AssetLoad = fn(&mut AssetsLoader, &str, &mut Context) -> Result<(), Box<dyn std::error::Error>>;
+
+/// Load every asset requested by the script of a gameobject
+fn load_assets(assets_loader: &mut AssetsLoader, go: &mut Gameobject, ctx: &mut Context) {
+    let loaders : [(&str, AssetLoad); 8] = [
+        ("textures", |loader, filename, ctx| loader.load_texture(filename, ctx).map(|_| ())),
+        ("sprite_sheets", |loader, filename, ctx| loader.load_sprite_sheet(filename, ctx).map(|_| ())),
+        ("animations", |loader, filename, ctx| loader.load_animation(filename, ctx).map(|_| ())),
+        ("aseprite", |loader, filename, ctx| loader.load_aseprite(filename, ctx).map(|_| ())),
+        ("tilemaps", |loader, filename, ctx| loader.load_tilemap(filename, ctx).map(|_| ())),
+        ("fonts", |loader, filename, ctx| loader.load_font(filename, ctx)),
+        ("shaders", |loader, filename, ctx| loader.load_shader(filename, ctx).map(|_| ())),
+        ("particles", |loader, filename, _| loader.load_particles(filename).map(|_| ())),
+    ];
+
+    for (kind, load) in loaders {
+        match go.get_assets(kind) {
+            Ok(assets) => {
+                for asset in assets.iter() {
+                    match load(assets_loader, asset.get_filename(), ctx) {
+                        Ok(_) => {},
+                        Err(err) => error!("Error when loading file {} : {}", asset.get_filename(), err),
+                    }
+                }
+            },
+            Err(err) => {
+                go.failed(err);
+                return;
+            }
+        }
+    }
 }
 
 fn date_to_string(date: DateTime<Utc>) -> String {
@@ -41,29 +165,62 @@ fn date_to_string(date: DateTime<Utc>) -> String {
 
 impl EventHandler for State {
     fn update(&mut self, _ctx: &mut ggez::Context) -> Result<(), ggez::GameError> {
+        self.assets_loader.reload_changed(_ctx);
+
+        let mouse = self.mouse(_ctx);
+        // A recording runs at a fixed time step, whatever time a frame takes to save
+        let delta = self.core.capture.borrow().recording_delta().unwrap_or(_ctx.time.delta().as_secs_f32());
+
+        let changed = self.core.watcher.changed();
+
         for go in self.core.gameobjects.iter_mut() {
             if go.is_loaded {
                 if go.is_assets_reloaded() {
-                    let assets : Table = go.get_script().get("Assets").unwrap();
-                    let files : Table = assets.get("textures").unwrap();
-            
-                    for data in files.pairs() {
-                        let unwraped = data.unwrap();
-            
-                        let _ : String = unwraped.0;
-                        let value : LuaAsset = unwraped.1;
-            
-                        match self.assets_loader.load_texture(&value.get_filename(), &_ctx) {
-                            Ok(_) => {},
-                            Err(err) => error!("Error when loading file {} : {}", value.get_filename(), err),
+                    load_assets(&mut self.assets_loader, go, _ctx);
+                }
+
+                go.set_mouse(&mouse);
+                go.update(delta);
+
+                // The update can fail and stop the gameobject
+                if go.is_loaded {
+                    for system in self.core.systems.iter() {
+                        if let Err(err) = system(go, &self.assets_loader, delta) {
+                            go.failed(err);
+                            break;
                         }
                     }
                 }
-    
-                go.update(_ctx.time.delta().as_secs_f32());
             }
 
-            go.watch();
+            go.watch(&changed);
+        }
+
+        let events = self.core.events.borrow_mut().drain();
+
+        for event in events.iter() {
+            for go in self.core.gameobjects.iter() {
+                go.dispatch(event);
+            }
+        }
+
+        let spawns = self.core.events.borrow_mut().drain_spawns();
+
+        for spawn in spawns {
+            let script = spawn.script.clone();
+
+            match self.core.spawn_gameobject(spawn) {
+                Ok(go) => go.init(),
+                Err(err) => error!("Cannot spawn {} : {}", script, err),
+            }
+        }
+
+        let despawns = self.core.events.borrow_mut().drain_despawns();
+
+        for id in despawns {
+            if !self.core.despawn_gameobject(&id) {
+                warn!("Cannot despawn {} : no gameobject has this id", id);
+            }
         }
 
         egui::Window::new("Log").show(&self.gui.ctx(), |window: &mut egui::Ui| {
@@ -111,17 +268,99 @@ impl EventHandler for State {
         Ok(())
     }
 
+    fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, _repeated: bool) -> Result<(), ggez::GameError> {
+        match input.keycode {
+            Some(KeyCode::Escape) => ctx.request_quit(),
+            Some(OVERLAY_KEY) => self.core.debug.borrow_mut().toggle_overlay(),
+            Some(SCREENSHOT_KEY) => self.core.capture.borrow_mut().screenshot(),
+            Some(RECORDING_KEY) => self.core.capture.borrow_mut().toggle_recording(),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
     fn draw(&mut self, _ctx: &mut ggez::Context) -> Result<(), ggez::GameError> {
-        let mut canvas = graphics::Canvas::from_frame(_ctx, Color::BLACK);
+        // The frame copied during the last draw was rendered when it ended
+        self.core.capture.borrow_mut().save_pending(_ctx);
 
-        for go in self.core.gameobjects.iter() {
+        for go in self.core.gameobjects.iter_mut() {
             if go.is_loaded {
-                go.render(_ctx, &mut canvas, &self.assets_loader)
+                go.render(_ctx, &mut self.renderer, &self.assets_loader)
             }
         }
 
+        let window = State::window(_ctx);
+        let screen = self.screen(_ctx);
+
+        let mut targets : Vec<(String, RenderTarget)> = self.core.gameobjects.iter_mut()
+            .filter(|go| go.is_loaded)
+            .filter_map(|go| go.render_target())
+            .filter(|(_, target)| target.active)
+            .collect();
+
+        targets.sort_by_key(|(_, target)| target.order);
+
+        let target_screens : HashMap<String, Rect> = targets.iter().map(|(name, target)| (name.clone(), target.screen())).collect();
+
+        let mut cameras : Vec<Camera> = self.core.gameobjects.iter_mut()
+            .filter(|go| go.is_loaded)
+            .filter_map(|go| go.camera(screen, &target_screens))
+            .filter(|camera| camera.active)
+            .collect();
+
+        cameras.sort_by_key(|camera| camera.order);
+
+        // Render targets are drawn first, so the window and the targets drawn after them can use their texture
+        for (name, target) in targets.iter() {
+            let image = self.assets_loader.load_render_target(name, target.width, target.height, _ctx);
+            let mut canvas = graphics::Canvas::from_image(_ctx, image, target.clear_color.to());
+
+            let target_cameras : Vec<&Camera> = cameras.iter().filter(|camera| camera.target.as_ref() == Some(name)).collect();
+
+            self.draw_view(_ctx, &mut canvas, &target_cameras, target.screen(), target.layers.as_deref(), Some(name))?;
+            canvas.finish(_ctx)?;
+        }
+
+        let virtual_screen = self.virtual_screen.clone();
+
+        let mut canvas = match &virtual_screen {
+            Some(image) => graphics::Canvas::from_image(_ctx, image.clone(), Color::BLACK),
+            None => graphics::Canvas::from_frame(_ctx, Color::BLACK)
+        };
+
+        let window_cameras : Vec<&Camera> = cameras.iter().filter(|camera| camera.target.is_none()).collect();
+        self.draw_view(_ctx, &mut canvas, &window_cameras, screen, None, None)?;
+
+        self.renderer.clear();
+
+        let mut upscale = Mat4::IDENTITY;
+
+        // The virtual screen is scaled by a whole factor with sharp pixels, black bars fill the rest of the window
+        if let (Some(image), Some(resolution)) = (&virtual_screen, &self.core.config.resolution) {
+            canvas.finish(_ctx)?;
+
+            let area = resolution.area(window);
+            let scale = resolution.scale(window);
+            upscale = Mat4::from_translation(Vec3::new(area.x, area.y, 0.0)) * Mat4::from_scale(Vec3::new(scale, scale, 1.0));
+
+            canvas = graphics::Canvas::from_frame(_ctx, Color::BLACK);
+            canvas.set_sampler(Sampler::nearest_clamp());
+            canvas.draw(image, DrawParam::default().dest(Vec2::new(area.x, area.y)).scale(Vec2::splat(scale)));
+            canvas.set_default_sampler();
+        }
+
+        // The debug shapes and the gui are drawn on top of the window, at its resolution
+        canvas.set_screen_coordinates(window);
+        canvas.set_default_scissor_rect();
+
+        self.draw_debug(_ctx, &mut canvas, &window_cameras, screen, upscale)?;
+        self.core.debug.borrow_mut().clear();
+
         canvas.draw(&self.gui, DrawParam::default().dest(Vec2::ZERO));
 
-        canvas.finish(_ctx)
+        canvas.finish(_ctx)?;
+
+        self.core.capture.borrow_mut().copy_frame(_ctx)
     }
 }
\ No newline at end of file
@@ -0,0 +1,41 @@
+use ggez::graphics::{self, TextAlign, TextLayout};
+
+use crate::components::Text;
+
+/// Font embedded in ggez, used by the texts without font
+pub const DEFAULT_FONT: &str = "LiberationMono-Regular";
+
+pub fn validate_align(align: &str) -> Result<(), String> {
+    match align {
+        "left" | "center" | "right" => Ok(()),
+        other => Err(format!("{} is not an alignment, expected left, center or right", other))
+    }
+}
+
+impl Text {
+    pub fn get_font(&self) -> &str {
+        self.font.as_deref().unwrap_or(DEFAULT_FONT)
+    }
+
+    /// ggez text of the component, its color is given by the draw param
+    pub fn build(&self) -> graphics::Text {
+        let mut text = graphics::Text::new(self.text.as_str());
+
+        text.set_font(self.get_font())
+            .set_scale(self.size)
+            .set_layout(TextLayout {
+                h_align: match self.align.as_str() {
+                    "center" => TextAlign::Middle,
+                    "right" => TextAlign::End,
+                    _ => TextAlign::Begin
+                },
+                v_align: TextAlign::Begin
+            });
+
+        if let Some(width) = self.wrap_width {
+            text.set_bounds([width, f32::INFINITY]).set_wrap(true);
+        }
+
+        text
+    }
+}
@@ -0,0 +1,813 @@
+use std::{collections::HashMap, error::Error, str::FromStr};
+
+use ggez::graphics::{self, Image, Mesh};
+use serde::Deserialize;
+
+use crate::{assets::{resolve_path, AssetsLoader}, components::Tilemap, events::Payload, gameobject::Gameobject};
+
+const FLIPPED_HORIZONTALLY: u32 = 0x80000000;
+const FLIPPED_VERTICALLY: u32 = 0x40000000;
+const FLIPPED_DIAGONALLY: u32 = 0x20000000;
+const FLIPPED_MASK: u32 = FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | 0x10000000;
+
+type FileReader<'a> = &'a mut dyn FnMut(&str) -> Result<Vec<u8>, Box<dyn Error>>;
+
+fn field(name: &str, value: Payload) -> (Payload, Payload) {
+    (Payload::String(name.to_string()), value)
+}
+
+fn json_to_payload(value: &serde_json::Value) -> Payload {
+    match value {
+        serde_json::Value::Null => Payload::Nil,
+        serde_json::Value::Bool(value) => Payload::Boolean(*value),
+        serde_json::Value::Number(value) => match value.as_i64() {
+            Some(value) => Payload::Integer(value),
+            None => Payload::Number(value.as_f64().unwrap_or_default())
+        },
+        serde_json::Value::String(value) => Payload::String(value.clone()),
+        serde_json::Value::Array(values) => Payload::Table(values.iter().enumerate()
+            .map(|(index, value)| (Payload::Integer(index as i64 + 1), json_to_payload(value))).collect()),
+        serde_json::Value::Object(values) => Payload::Table(values.iter()
+            .map(|(key, value)| field(key, json_to_payload(value))).collect())
+    }
+}
+
+/// Uv of the corners of a tile (top left, top right, bottom right, bottom left) once the flip flags of its gid are applied
+fn tile_uv(raw_gid: u32, mut uv: [[f32; 2]; 4]) -> [[f32; 2]; 4] {
+    if raw_gid & FLIPPED_DIAGONALLY != 0 {
+        uv.swap(1, 3);
+    }
+
+    if raw_gid & FLIPPED_HORIZONTALLY != 0 {
+        uv = [uv[1], uv[0], uv[3], uv[2]];
+    }
+
+    if raw_gid & FLIPPED_VERTICALLY != 0 {
+        uv = [uv[3], uv[2], uv[1], uv[0]];
+    }
+
+    uv
+}
+
+/// Tileset with its image, the image path is relative to the assets folder
+#[derive(Clone, Debug)]
+pub struct Tileset {
+    pub first_gid: u32,
+    pub image: String,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub columns: u32,
+    pub tile_count: u32,
+    pub margin: u32,
+    pub spacing: u32
+}
+
+#[derive(Clone, Debug)]
+pub struct TileLayer {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Global tile ids, with the flip flags set by tiled
+    pub data: Vec<u32>,
+    pub opacity: f32,
+    pub offset_x: f32,
+    pub offset_y: f32
+}
+
+#[derive(Clone, Debug)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    pub class: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub rotation: f32,
+    pub properties: Payload
+}
+
+#[derive(Clone, Debug)]
+pub struct ObjectLayer {
+    pub name: String,
+    pub objects: Vec<MapObject>
+}
+
+/// Mesh of every tile of a layer using the same tileset
+pub struct LayerMesh {
+    pub texture: String,
+    pub mesh: Mesh
+}
+
+/// Map made with tiled, loaded from a json (.tmj, .json) or a tmx file
+pub struct TiledMap {
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub properties: Payload,
+    pub tilesets: Vec<Tileset>,
+    pub tile_layers: Vec<TileLayer>,
+    pub object_layers: Vec<ObjectLayer>,
+    meshes: Vec<LayerMesh>
+}
+
+#[derive(Deserialize)]
+struct JsonProperty {
+    name: String,
+    value: serde_json::Value
+}
+
+fn json_properties(properties: &[JsonProperty]) -> Payload {
+    Payload::Table(properties.iter().map(|property| field(&property.name, json_to_payload(&property.value))).collect())
+}
+
+#[derive(Deserialize)]
+struct JsonTileset {
+    firstgid: Option<u32>,
+    source: Option<String>,
+    image: Option<String>,
+    #[serde(default)]
+    tilewidth: u32,
+    #[serde(default)]
+    tileheight: u32,
+    #[serde(default)]
+    columns: u32,
+    #[serde(default)]
+    tilecount: u32,
+    #[serde(default)]
+    margin: u32,
+    #[serde(default)]
+    spacing: u32
+}
+
+#[derive(Deserialize)]
+struct JsonObject {
+    #[serde(default)]
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "type")]
+    object_type: String,
+    #[serde(default)]
+    class: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default)]
+    properties: Vec<JsonProperty>
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum JsonLayer {
+    Tilelayer {
+        name: String,
+        width: u32,
+        height: u32,
+        data: Option<serde_json::Value>,
+        #[serde(default = "default_true")]
+        visible: bool,
+        #[serde(default = "default_opacity")]
+        opacity: f32,
+        #[serde(default)]
+        offsetx: f32,
+        #[serde(default)]
+        offsety: f32
+    },
+    Objectgroup {
+        name: String,
+        #[serde(default)]
+        objects: Vec<JsonObject>
+    },
+    Group {
+        #[serde(default)]
+        layers: Vec<JsonLayer>,
+        #[serde(default = "default_true")]
+        visible: bool,
+        #[serde(default)]
+        offsetx: f32,
+        #[serde(default)]
+        offsety: f32
+    },
+    Imagelayer {}
+}
+
+#[derive(Deserialize)]
+struct JsonMap {
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    #[serde(default)]
+    infinite: bool,
+    #[serde(default)]
+    layers: Vec<JsonLayer>,
+    #[serde(default)]
+    tilesets: Vec<JsonTileset>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+/// Minimal xml tree, enough to read tmx, tsx and bitmap font files
+pub struct XmlElement {
+    pub name: String,
+    pub attributes: HashMap<String, String>,
+    pub children: Vec<XmlElement>,
+    pub text: String
+}
+
+impl XmlElement {
+    pub fn parse(file: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let mut stack : Vec<XmlElement> = Vec::new();
+
+        for event in xml::reader::EventReader::new(file) {
+            match event? {
+                xml::reader::XmlEvent::StartElement { name, attributes, .. } => stack.push(XmlElement {
+                    name: name.local_name,
+                    attributes: attributes.into_iter().map(|attribute| (attribute.name.local_name, attribute.value)).collect(),
+                    children: Vec::new(),
+                    text: String::new()
+                }),
+                xml::reader::XmlEvent::EndElement { .. } => {
+                    let element = stack.pop().ok_or("Unexpected end of xml element")?;
+
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element)
+                    }
+                },
+                xml::reader::XmlEvent::Characters(text) | xml::reader::XmlEvent::CData(text) => {
+                    if let Some(element) = stack.last_mut() {
+                        element.text.push_str(&text);
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        Err("Empty xml file".into())
+    }
+
+    pub fn attribute<T: FromStr>(&self, name: &str) -> Result<T, Box<dyn Error>> {
+        let value = self.attributes.get(name).ok_or_else(|| format!("Missing attribute {} on <{}>", name, self.name))?;
+        value.parse::<T>().map_err(|_| format!("Invalid attribute {}=\"{}\" on <{}>", name, value, self.name).into())
+    }
+
+    pub fn attribute_or<T: FromStr>(&self, name: &str, default: T) -> Result<T, Box<dyn Error>> {
+        match self.attributes.contains_key(name) {
+            true => self.attribute(name),
+            false => Ok(default)
+        }
+    }
+
+    fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn properties(&self) -> Payload {
+        let properties = match self.child("properties") {
+            Some(properties) => properties,
+            None => return Payload::Table(Vec::new())
+        };
+
+        Payload::Table(properties.children.iter().filter(|property| property.name == "property").map(|property| {
+            let name = property.attributes.get("name").cloned().unwrap_or_default();
+            let value = property.attributes.get("value").cloned().unwrap_or_else(|| property.text.clone());
+
+            let value = match property.attributes.get("type").map(|value_type| value_type.as_str()) {
+                Some("int") => value.parse().map(Payload::Integer).unwrap_or(Payload::Nil),
+                Some("float") => value.parse().map(Payload::Number).unwrap_or(Payload::Nil),
+                Some("bool") => Payload::Boolean(value == "true"),
+                _ => Payload::String(value)
+            };
+
+            field(&name, value)
+        }).collect())
+    }
+}
+
+impl Tileset {
+    fn from_json(filename: &str, tileset: &JsonTileset, first_gid: u32) -> Result<Self, Box<dyn Error>> {
+        let image = tileset.image.as_ref().ok_or("Tilesets made of a collection of images are not supported")?;
+
+        Ok(Self {
+            first_gid,
+            image: resolve_path(filename, image),
+            tile_width: tileset.tilewidth,
+            tile_height: tileset.tileheight,
+            columns: tileset.columns,
+            tile_count: tileset.tilecount,
+            margin: tileset.margin,
+            spacing: tileset.spacing
+        })
+    }
+
+    fn from_xml(filename: &str, tileset: &XmlElement, first_gid: u32) -> Result<Self, Box<dyn Error>> {
+        let image = tileset.child("image").ok_or("Tilesets made of a collection of images are not supported")?;
+
+        Ok(Self {
+            first_gid,
+            image: resolve_path(filename, &image.attribute::<String>("source")?),
+            tile_width: tileset.attribute("tilewidth")?,
+            tile_height: tileset.attribute("tileheight")?,
+            columns: tileset.attribute("columns")?,
+            tile_count: tileset.attribute("tilecount")?,
+            margin: tileset.attribute_or("margin", 0)?,
+            spacing: tileset.attribute_or("spacing", 0)?
+        })
+    }
+
+    /// Load a tileset from an external file (.tsj, .json or .tsx)
+    fn from_file(filename: &str, first_gid: u32, read: FileReader) -> Result<Self, Box<dyn Error>> {
+        let file = read(filename)?;
+
+        if filename.ends_with(".tsx") {
+            Self::from_xml(filename, &XmlElement::parse(&file)?, first_gid)
+        } else {
+            Self::from_json(filename, &serde_json::from_slice(&file)?, first_gid)
+        }
+    }
+}
+
+impl TiledMap {
+    pub fn from_json(filename: &str, file: &[u8], read: FileReader) -> Result<Self, Box<dyn Error>> {
+        let json : JsonMap = serde_json::from_slice(file)?;
+
+        if json.infinite {
+            return Err("Infinite maps are not supported".into());
+        }
+
+        let mut map = Self::new(json.width, json.height, json.tilewidth, json.tileheight, json_properties(&json.properties));
+
+        for tileset in json.tilesets.iter() {
+            let first_gid = tileset.firstgid.ok_or("Tileset without firstgid")?;
+
+            map.tilesets.push(match &tileset.source {
+                Some(source) => Tileset::from_file(&resolve_path(filename, source), first_gid, read)?,
+                None => Tileset::from_json(filename, tileset, first_gid)?
+            });
+        }
+
+        map.add_json_layers(&json.layers, 0.0, 0.0)?;
+
+        Ok(map)
+    }
+
+    fn add_json_layers(&mut self, layers: &[JsonLayer], offset_x: f32, offset_y: f32) -> Result<(), Box<dyn Error>> {
+        for layer in layers {
+            match layer {
+                JsonLayer::Tilelayer { name, width, height, data, visible, opacity, offsetx, offsety } => {
+                    if !visible {
+                        continue;
+                    }
+
+                    let data = match data {
+                        Some(serde_json::Value::Array(data)) => data.iter().map(|gid| gid.as_u64().unwrap_or(0) as u32).collect(),
+                        _ => return Err(format!("Layer {} need to use the csv tile layer format", name).into())
+                    };
+
+                    self.tile_layers.push(TileLayer {
+                        name: name.clone(),
+                        width: *width,
+                        height: *height,
+                        data,
+                        opacity: *opacity,
+                        offset_x: offset_x + offsetx,
+                        offset_y: offset_y + offsety
+                    });
+                },
+                JsonLayer::Objectgroup { name, objects } => self.object_layers.push(ObjectLayer {
+                    name: name.clone(),
+                    objects: objects.iter().map(|object| MapObject {
+                        id: object.id,
+                        name: object.name.clone(),
+                        class: if object.class.is_empty() { object.object_type.clone() } else { object.class.clone() },
+                        x: object.x + offset_x,
+                        y: object.y + offset_y,
+                        width: object.width,
+                        height: object.height,
+                        rotation: object.rotation,
+                        properties: json_properties(&object.properties)
+                    }).collect()
+                }),
+                JsonLayer::Group { layers, visible, offsetx, offsety } => {
+                    if *visible {
+                        self.add_json_layers(layers, offset_x + offsetx, offset_y + offsety)?;
+                    }
+                },
+                JsonLayer::Imagelayer {} => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn from_tmx(filename: &str, file: &[u8], read: FileReader) -> Result<Self, Box<dyn Error>> {
+        let xml = XmlElement::parse(file)?;
+
+        if xml.attribute_or("infinite", 0)? != 0 {
+            return Err("Infinite maps are not supported".into());
+        }
+
+        let mut map = Self::new(xml.attribute("width")?, xml.attribute("height")?, xml.attribute("tilewidth")?, xml.attribute("tileheight")?, xml.properties());
+
+        for tileset in xml.children.iter().filter(|child| child.name == "tileset") {
+            let first_gid = tileset.attribute("firstgid")?;
+
+            map.tilesets.push(match tileset.attributes.get("source") {
+                Some(source) => Tileset::from_file(&resolve_path(filename, source), first_gid, read)?,
+                None => Tileset::from_xml(filename, tileset, first_gid)?
+            });
+        }
+
+        map.add_xml_layers(&xml, 0.0, 0.0)?;
+
+        Ok(map)
+    }
+
+    fn add_xml_layers(&mut self, parent: &XmlElement, offset_x: f32, offset_y: f32) -> Result<(), Box<dyn Error>> {
+        for layer in parent.children.iter() {
+            if layer.attribute_or("visible", 1)? == 0 {
+                continue;
+            }
+
+            let layer_offset_x = offset_x + layer.attribute_or("offsetx", 0.0)?;
+            let layer_offset_y = offset_y + layer.attribute_or("offsety", 0.0)?;
+
+            match layer.name.as_str() {
+                "layer" => {
+                    let name : String = layer.attribute_or("name", String::new())?;
+                    let data = layer.child("data").ok_or_else(|| format!("Layer {} without data", name))?;
+
+                    let data = match data.attributes.get("encoding").map(|encoding| encoding.as_str()) {
+                        Some("csv") => data.text.split(',').map(|gid| gid.trim().parse::<u32>()).collect::<Result<Vec<u32>, _>>()?,
+                        None => data.children.iter().map(|tile| tile.attribute_or("gid", 0)).collect::<Result<Vec<u32>, _>>()?,
+                        Some(_) => return Err(format!("Layer {} need to use the csv tile layer format", name).into())
+                    };
+
+                    self.tile_layers.push(TileLayer {
+                        name,
+                        width: layer.attribute("width")?,
+                        height: layer.attribute("height")?,
+                        data,
+                        opacity: layer.attribute_or("opacity", 1.0)?,
+                        offset_x: layer_offset_x,
+                        offset_y: layer_offset_y
+                    });
+                },
+                "objectgroup" => {
+                    let mut objects = Vec::new();
+
+                    for object in layer.children.iter().filter(|child| child.name == "object") {
+                        let class = match object.attributes.get("class") {
+                            Some(class) => class.clone(),
+                            None => object.attribute_or("type", String::new())?
+                        };
+
+                        objects.push(MapObject {
+                            id: object.attribute_or("id", 0)?,
+                            name: object.attribute_or("name", String::new())?,
+                            class,
+                            x: object.attribute::<f32>("x")? + layer_offset_x,
+                            y: object.attribute::<f32>("y")? + layer_offset_y,
+                            width: object.attribute_or("width", 0.0)?,
+                            height: object.attribute_or("height", 0.0)?,
+                            rotation: object.attribute_or("rotation", 0.0)?,
+                            properties: object.properties()
+                        });
+                    }
+
+                    self.object_layers.push(ObjectLayer { name: layer.attribute_or("name", String::new())?, objects });
+                },
+                "group" => self.add_xml_layers(layer, layer_offset_x, layer_offset_y)?,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn new(width: u32, height: u32, tile_width: u32, tile_height: u32, properties: Payload) -> Self {
+        Self {
+            width,
+            height,
+            tile_width,
+            tile_height,
+            properties,
+            tilesets: Vec::new(),
+            tile_layers: Vec::new(),
+            object_layers: Vec::new(),
+            meshes: Vec::new()
+        }
+    }
+
+    fn find_tileset(&self, gid: u32) -> Option<usize> {
+        self.tilesets.iter().rposition(|tileset| tileset.first_gid <= gid)
+    }
+
+    /// Build one mesh per layer and tileset, the tileset images need to be loaded
+    pub fn build_meshes(&mut self, ctx: &ggez::Context, get_texture: impl Fn(&str) -> Option<Image>) -> Result<(), Box<dyn Error>> {
+        let mut meshes = Vec::new();
+
+        for layer in self.tile_layers.iter() {
+            if layer.data.len() != (layer.width * layer.height) as usize {
+                return Err(format!("Layer {} has {} tiles instead of {}x{}", layer.name, layer.data.len(), layer.width, layer.height).into());
+            }
+
+            let mut batches : Vec<(Vec<graphics::Vertex>, Vec<u32>)> = vec![(Vec::new(), Vec::new()); self.tilesets.len()];
+
+            for (index, raw_gid) in layer.data.iter().enumerate() {
+                let gid = raw_gid & !FLIPPED_MASK;
+
+                if gid == 0 || layer.width == 0 {
+                    continue;
+                }
+
+                let tileset_index = match self.find_tileset(gid) {
+                    Some(tileset_index) => tileset_index,
+                    None => continue
+                };
+
+                let tileset = &self.tilesets[tileset_index];
+                let local_id = gid - tileset.first_gid;
+
+                if local_id >= tileset.tile_count || tileset.columns == 0 {
+                    continue;
+                }
+
+                let texture = get_texture(&tileset.image).ok_or_else(|| format!("Tileset image {} is not loaded", tileset.image))?;
+                let (texture_width, texture_height) = (texture.width() as f32, texture.height() as f32);
+
+                let source_x = (tileset.margin + (local_id % tileset.columns) * (tileset.tile_width + tileset.spacing)) as f32;
+                let source_y = (tileset.margin + (local_id / tileset.columns) * (tileset.tile_height + tileset.spacing)) as f32;
+
+                let (left, top) = (source_x / texture_width, source_y / texture_height);
+                let (right, bottom) = ((source_x + tileset.tile_width as f32) / texture_width, (source_y + tileset.tile_height as f32) / texture_height);
+                let uv = tile_uv(*raw_gid, [[left, top], [right, top], [right, bottom], [left, bottom]]);
+
+                // Tiles bigger than the map tiles are aligned on the bottom left corner of their cell
+                let x = (index as u32 % layer.width * self.tile_width) as f32 + layer.offset_x;
+                let y = ((index as u32 / layer.width + 1) * self.tile_height) as f32 - tileset.tile_height as f32 + layer.offset_y;
+                let (w, h) = (tileset.tile_width as f32, tileset.tile_height as f32);
+
+                let (vertices, indices) = &mut batches[tileset_index];
+                let first = vertices.len() as u32;
+                let color = [1.0, 1.0, 1.0, layer.opacity];
+
+                vertices.push(graphics::Vertex { position: [x, y], uv: uv[0], color });
+                vertices.push(graphics::Vertex { position: [x + w, y], uv: uv[1], color });
+                vertices.push(graphics::Vertex { position: [x + w, y + h], uv: uv[2], color });
+                vertices.push(graphics::Vertex { position: [x, y + h], uv: uv[3], color });
+                indices.extend_from_slice(&[first, first + 1, first + 2, first + 2, first + 3, first]);
+            }
+
+            for (tileset_index, (vertices, indices)) in batches.iter().enumerate() {
+                if vertices.is_empty() {
+                    continue;
+                }
+
+                meshes.push(LayerMesh {
+                    texture: self.tilesets[tileset_index].image.clone(),
+                    mesh: Mesh::from_data(ctx, graphics::MeshData { vertices, indices })
+                });
+            }
+        }
+
+        self.meshes = meshes;
+
+        Ok(())
+    }
+
+    pub fn get_meshes(&self) -> &Vec<LayerMesh> {
+        &self.meshes
+    }
+
+    /// Size and custom properties of the map as a lua table
+    pub fn info_payload(&self) -> Payload {
+        Payload::Table(vec![
+            field("width", Payload::Integer(self.width as i64)),
+            field("height", Payload::Integer(self.height as i64)),
+            field("tile_width", Payload::Integer(self.tile_width as i64)),
+            field("tile_height", Payload::Integer(self.tile_height as i64)),
+            field("properties", self.properties.clone())
+        ])
+    }
+
+    /// Objects of every object layer as a lua array
+    pub fn objects_payload(&self) -> Payload {
+        let mut objects = Vec::new();
+
+        for layer in self.object_layers.iter() {
+            for object in layer.objects.iter() {
+                objects.push((Payload::Integer(objects.len() as i64 + 1), Payload::Table(vec![
+                    field("id", Payload::Integer(object.id as i64)),
+                    field("name", Payload::String(object.name.clone())),
+                    field("class", Payload::String(object.class.clone())),
+                    field("layer", Payload::String(layer.name.clone())),
+                    field("x", Payload::Number(object.x as f64)),
+                    field("y", Payload::Number(object.y as f64)),
+                    field("width", Payload::Number(object.width as f64)),
+                    field("height", Payload::Number(object.height as f64)),
+                    field("rotation", Payload::Number(object.rotation as f64)),
+                    field("properties", object.properties.clone())
+                ])));
+            }
+        }
+
+        Payload::Table(objects)
+    }
+}
+
+/// System giving the objects of the map to the `Tilemap` lua component once the map is loaded
+pub fn expose_objects(go: &Gameobject, assets: &AssetsLoader, _: f32) -> mlua::Result<()> {
+    let tilemap = match go.get_component::<Tilemap>()? {
+        Some(tilemap) => tilemap,
+        None => return Ok(())
+    };
+
+    if tilemap.loaded {
+        return Ok(());
+    }
+
+    match tilemap.map.as_ref().and_then(|map| assets.get_tilemap(map)) {
+        Some(map) => go.call_component("Tilemap", "load", (map.objects_payload(), map.info_payload())),
+        None => Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILES : [[f32; 2]; 4] = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+
+    fn no_file(filename: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        Err(format!("Unexpected read of {}", filename).into())
+    }
+
+    #[test]
+    fn json_map() {
+        let file = br#"{
+            "width": 2, "height": 1, "tilewidth": 16, "tileheight": 16,
+            "properties": [{ "name": "music", "type": "string", "value": "cave.ogg" }],
+            "tilesets": [{ "firstgid": 1, "image": "tiles.png", "tilewidth": 16, "tileheight": 16, "columns": 4, "tilecount": 16 }],
+            "layers": [
+                { "type": "tilelayer", "name": "ground", "width": 2, "height": 1, "data": [1, 2147483650] },
+                { "type": "tilelayer", "name": "hidden", "width": 2, "height": 1, "data": [1, 1], "visible": false },
+                { "type": "objectgroup", "name": "spawns", "objects": [
+                    { "id": 3, "name": "player", "type": "Spawn", "x": 8, "y": 4, "properties": [{ "name": "health", "type": "int", "value": 3 }] }
+                ]}
+            ]
+        }"#;
+
+        let map = TiledMap::from_json("levels/cave.tmj", file, &mut no_file).unwrap();
+
+        assert_eq!((map.width, map.height, map.tile_width, map.tile_height), (2, 1, 16, 16));
+        assert!(matches!(map.properties.get("music"), Some(Payload::String(music)) if music == "cave.ogg"));
+        assert_eq!(map.tilesets[0].image, "levels/tiles.png");
+        assert_eq!(map.tile_layers.len(), 1);
+        assert_eq!(map.tile_layers[0].data, vec![1, 0x80000002]);
+
+        let object = &map.object_layers[0].objects[0];
+        assert_eq!((object.id, object.name.as_str(), object.class.as_str(), object.x, object.y), (3, "player", "Spawn", 8.0, 4.0));
+        assert!(matches!(object.properties.get("health"), Some(Payload::Integer(3))));
+    }
+
+    #[test]
+    fn json_group_offsets() {
+        let file = br#"{
+            "width": 1, "height": 1, "tilewidth": 8, "tileheight": 8,
+            "layers": [{ "type": "group", "offsetx": 10, "offsety": 20, "layers": [
+                { "type": "tilelayer", "name": "ground", "width": 1, "height": 1, "data": [0], "offsetx": 1, "offsety": 2 },
+                { "type": "objectgroup", "name": "spawns", "objects": [{ "x": 5, "y": 5 }] },
+                { "type": "group", "visible": false, "layers": [
+                    { "type": "tilelayer", "name": "hidden", "width": 1, "height": 1, "data": [0] }
+                ]}
+            ]}]
+        }"#;
+
+        let map = TiledMap::from_json("map.tmj", file, &mut no_file).unwrap();
+
+        assert_eq!(map.tile_layers.len(), 1);
+        assert_eq!((map.tile_layers[0].offset_x, map.tile_layers[0].offset_y), (11.0, 22.0));
+        assert_eq!((map.object_layers[0].objects[0].x, map.object_layers[0].objects[0].y), (15.0, 25.0));
+    }
+
+    #[test]
+    fn json_external_tileset() {
+        let file = br#"{
+            "width": 1, "height": 1, "tilewidth": 8, "tileheight": 8,
+            "tilesets": [{ "firstgid": 5, "source": "../tilesets/forest.tsj" }]
+        }"#;
+
+        let mut read = |filename: &str| -> Result<Vec<u8>, Box<dyn Error>> {
+            assert_eq!(filename, "tilesets/forest.tsj");
+            Ok(br#"{ "image": "forest.png", "tilewidth": 8, "tileheight": 8, "columns": 2, "tilecount": 4, "margin": 1, "spacing": 2 }"#.to_vec())
+        };
+
+        let map = TiledMap::from_json("levels/forest.tmj", file, &mut read).unwrap();
+        let tileset = &map.tilesets[0];
+
+        assert_eq!((tileset.first_gid, tileset.image.as_str()), (5, "tilesets/forest.png"));
+        assert_eq!((tileset.columns, tileset.tile_count, tileset.margin, tileset.spacing), (2, 4, 1, 2));
+    }
+
+    #[test]
+    fn json_rejects_base64_layers() {
+        let file = br#"{
+            "width": 1, "height": 1, "tilewidth": 8, "tileheight": 8,
+            "layers": [{ "type": "tilelayer", "name": "ground", "width": 1, "height": 1, "data": "AQAAAA==", "encoding": "base64" }]
+        }"#;
+
+        assert!(TiledMap::from_json("map.tmj", file, &mut no_file).is_err());
+    }
+
+    #[test]
+    fn tmx_map() {
+        let file = br#"<?xml version="1.0" encoding="UTF-8"?>
+            <map width="2" height="2" tilewidth="16" tileheight="16" infinite="0">
+                <properties>
+                    <property name="dark" type="bool" value="true"/>
+                    <property name="gravity" type="float" value="9.5"/>
+                </properties>
+                <tileset firstgid="1" tilewidth="16" tileheight="16" columns="4" tilecount="16">
+                    <image source="tiles.png" width="64" height="64"/>
+                </tileset>
+                <layer name="ground" width="2" height="2" opacity="0.5">
+                    <data encoding="csv">1,2,
+                    0,1073741827</data>
+                </layer>
+                <group offsetx="4" offsety="8">
+                    <layer name="top" width="1" height="1" offsetx="1">
+                        <data><tile gid="3"/></data>
+                    </layer>
+                    <objectgroup name="spawns">
+                        <object id="1" name="door" class="Door" x="2" y="3" width="16" height="32"/>
+                    </objectgroup>
+                </group>
+                <layer name="hidden" width="1" height="1" visible="0">
+                    <data encoding="csv">1</data>
+                </layer>
+            </map>"#;
+
+        let map = TiledMap::from_tmx("maps/house.tmx", file, &mut no_file).unwrap();
+
+        assert!(matches!(map.properties.get("dark"), Some(Payload::Boolean(true))));
+        assert!(matches!(map.properties.get("gravity"), Some(Payload::Number(gravity)) if *gravity == 9.5));
+        assert_eq!(map.tilesets[0].image, "maps/tiles.png");
+
+        assert_eq!(map.tile_layers.len(), 2);
+        assert_eq!(map.tile_layers[0].data, vec![1, 2, 0, 0x40000003]);
+        assert_eq!(map.tile_layers[0].opacity, 0.5);
+        assert_eq!(map.tile_layers[1].data, vec![3]);
+        assert_eq!((map.tile_layers[1].offset_x, map.tile_layers[1].offset_y), (5.0, 8.0));
+
+        let object = &map.object_layers[0].objects[0];
+        assert_eq!((object.name.as_str(), object.class.as_str(), object.x, object.y, object.height), ("door", "Door", 6.0, 11.0, 32.0));
+    }
+
+    #[test]
+    fn tmx_rejects_infinite_maps() {
+        let file = br#"<map width="1" height="1" tilewidth="8" tileheight="8" infinite="1"></map>"#;
+
+        assert!(TiledMap::from_tmx("map.tmx", file, &mut no_file).is_err());
+    }
+
+    #[test]
+    fn gid_flip_flags() {
+        assert_eq!(tile_uv(1, TILES), TILES);
+        assert_eq!(tile_uv(1 | FLIPPED_HORIZONTALLY, TILES), [[1.0, 0.0], [0.0, 0.0], [0.0, 1.0], [1.0, 1.0]]);
+        assert_eq!(tile_uv(1 | FLIPPED_VERTICALLY, TILES), [[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]]);
+        assert_eq!(tile_uv(1 | FLIPPED_DIAGONALLY, TILES), [[0.0, 0.0], [0.0, 1.0], [1.0, 1.0], [1.0, 0.0]]);
+        // Tiled rotates a tile by 90 degrees clockwise with the diagonal and horizontal flags
+        assert_eq!(tile_uv(1 | FLIPPED_DIAGONALLY | FLIPPED_HORIZONTALLY, TILES), [[0.0, 1.0], [0.0, 0.0], [1.0, 0.0], [1.0, 1.0]]);
+    }
+
+    #[test]
+    fn find_tileset_by_gid() {
+        let mut map = TiledMap::new(1, 1, 8, 8, Payload::Nil);
+
+        for first_gid in [1, 17] {
+            map.tilesets.push(Tileset { first_gid, image: String::new(), tile_width: 8, tile_height: 8, columns: 4, tile_count: 16, margin: 0, spacing: 0 });
+        }
+
+        assert_eq!(map.find_tileset(0), None);
+        assert_eq!(map.find_tileset(16), Some(0));
+        assert_eq!(map.find_tileset(17), Some(1));
+        assert_eq!(map.find_tileset(0x80000011 & !FLIPPED_MASK), Some(1));
+    }
+}